@@ -0,0 +1,100 @@
+//! Benchmarks on large generated inputs, for the days that have more than one way of doing things.
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SIZES: [usize; 2] = [1 << 20, 8 << 20];
+
+/// Rucksacks with random contents, in groups of three, adding up to roughly `size` bytes.
+fn rucksacks(rng: &mut fastrand::Rng, size: usize) -> String {
+    let mut input = String::with_capacity(size + 256);
+    while input.len() < size {
+        for _ in 0..3 {
+            let len = 2 * rng.usize(4..24);
+            input.extend((0..len).map(|_| rng.alphabetic()));
+            input.push('\n');
+        }
+    }
+    input
+}
+
+/// A signal of `size` bytes with too few different letters in it for a marker, except for one at the very end.
+fn signal(rng: &mut fastrand::Rng, size: usize) -> Vec<u8> {
+    let mut signal = (0..size - 14).map(|_| rng.u8(b'a'..b'n')).collect::<Vec<_>>();
+    signal.extend(b"nopqrstuvwxyza");
+    signal
+}
+
+/// A square forest of trees with random heights, with about `size` trees in it.
+fn forest(rng: &mut fastrand::Rng, size: usize) -> String {
+    let side = (size as f64).sqrt() as usize;
+    let mut input = String::with_capacity((side + 1) * side);
+    for _ in 0..side {
+        input.extend((0..side).map(|_| rng.digit(10)));
+        input.push('\n');
+    }
+    input
+}
+
+fn day03(c: &mut Criterion) {
+    let mut rng = fastrand::Rng::with_seed(0x2022_0003);
+    let mut group = c.benchmark_group("day03");
+    for size in SIZES {
+        let input = rucksacks(&mut rng, size);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("scalar", size), &input, |b, input| {
+            b.iter(|| day03::Audit::new(input, 2, 3).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("simd", size), &input, |b, input| {
+            b.iter(|| day03::Audit::with_parser(input, 2, 3, day03::simd::rucksack_parser()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn day06(c: &mut Criterion) {
+    use day06::Algorithm;
+
+    let mut rng = fastrand::Rng::with_seed(0x2022_0006);
+    let mut group = c.benchmark_group("day06");
+    for size in SIZES {
+        let input = signal(&mut rng, size);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        for (name, algorithm) in [("counting", Algorithm::Counting), ("bitmask", Algorithm::Bitmask)] {
+            group.bench_with_input(BenchmarkId::new(name, size), &input, |b, input| {
+                b.iter(|| day06::first_marker(input, 14, algorithm))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn day08(c: &mut Criterion) {
+    use day08::Algorithm;
+
+    let mut rng = fastrand::Rng::with_seed(0x2022_0008);
+    let mut group = c.benchmark_group("day08");
+    for size in SIZES {
+        let grid = forest(&mut rng, size).parse::<day08::Grid>().unwrap();
+        group.throughput(Throughput::Elements((grid.width() * grid.height()) as u64));
+        for (name, algorithm) in [("walk", Algorithm::Walk), ("stack", Algorithm::Stack)] {
+            group.bench_with_input(BenchmarkId::new(name, size), &grid, |b, grid| {
+                b.iter(|| day08::survey(grid, algorithm))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+
+    config = Criterion::default()
+        .sample_size(20)
+        .measurement_time(Duration::from_secs(10))
+        .warm_up_time(Duration::from_secs(3));
+
+    targets = day03, day06, day08
+}
+
+criterion_main!(benches);
@@ -1,20 +1,175 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display, num::ParseIntError, str::Lines};
 
 const TOP_N: usize = 3;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A line that isn't a calorie count.
+    BadLine {
+        elf: usize,
+        line: usize,
+        source: ParseIntError,
+    },
+    /// An elf whose calories don't fit in a `u64`.
+    Overflow { elf: usize, line: usize },
+    /// The top elves' calories add up to more than fits in a `u64`.
+    TopOverflow,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::BadLine { elf, line, source } => {
+                write!(f, "line {line} (elf {elf}): invalid calorie count: {source}")
+            }
+            ParseError::Overflow { elf, line } => write!(f, "line {line} (elf {elf}): calorie total overflowed"),
+            ParseError::TopOverflow => write!(f, "calorie total of the top {TOP_N} elves overflowed"),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::BadLine { source, .. } => Some(source),
+            ParseError::Overflow { .. } | ParseError::TopOverflow => None,
+        }
+    }
+}
+
+/// Iterator over the total calories carried by each elf.
+///
+/// Elves are separated by one or more blank lines; both `\n` and `\r\n` line endings are accepted.
+/// Elf indices in errors start at zero, line numbers start at one. After an error the rest of that elf's lines are
+/// skipped, so iteration carries on with the next elf.
+pub struct Elves<'a> {
+    lines: std::iter::Enumerate<Lines<'a>>,
+    elf: usize,
+}
+
+impl<'a> Elves<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            lines: input.lines().enumerate(),
+            elf: 0,
+        }
+    }
+}
+
+impl Elves<'_> {
+    /// Give up on the current elf, moving on to the next one.
+    fn skip_elf(&mut self) {
+        for (_, line) in self.lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+        self.elf += 1;
+    }
+}
+
+impl Iterator for Elves<'_> {
+    type Item = Result<u64, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut total = None;
+        for (idx, line) in self.lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() {
+                if total.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            let (elf, line_no) = (self.elf, idx + 1);
+            let sum = match line.parse::<u64>() {
+                Ok(item) => total
+                    .unwrap_or(0_u64)
+                    .checked_add(item)
+                    .ok_or(ParseError::Overflow { elf, line: line_no }),
+                Err(source) => Err(ParseError::BadLine {
+                    elf,
+                    line: line_no,
+                    source,
+                }),
+            };
+            match sum {
+                Ok(sum) => total = Some(sum),
+                Err(err) => {
+                    self.skip_elf();
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        let total = total?;
+        self.elf += 1;
+        Some(Ok(total))
+    }
+}
+
+fn do_solve(input: &str) -> Result<(u64, u64), ParseError> {
+    let mut biggest = [0; TOP_N];
+
+    Elves::new(input).try_for_each(|elf| {
+        let elf = elf?;
+        if let Some(i) = biggest.iter().position(|&n| elf >= n) {
+            biggest[i..].rotate_right(1);
+            biggest[i] = elf;
+        }
+        Ok(())
+    })?;
+
+    let top = biggest
+        .into_iter()
+        .try_fold(0_u64, u64::checked_add)
+        .ok_or(ParseError::TopOverflow)?;
+    Ok((biggest[0], top))
+}
+
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    let mut biggest = [0; TOP_N];
+    do_solve(include_str!("input.txt")).unwrap()
+}
 
-    include_str!("input.txt")
-        .split("\n\n")
-        .map(|elf| elf.trim().split('\n').map(|n| n.parse::<u64>().unwrap()).sum())
-        .for_each(|elf| {
-            if let Some(i) = biggest.iter().position(|&n| elf >= n) {
-                biggest[i..].rotate_right(1);
-                biggest[i] = elf;
-            }
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separators() {
+        let input = "1000\r\n2000\r\n\r\n\r\n\r\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n\n";
+        assert_eq!(do_solve(input), Ok((24000, 45000)));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let Err(ParseError::BadLine { elf, line, .. }) = do_solve("1\n2\n\n3\nfour\n") else {
+            panic!("expected a bad line");
+        };
+        assert_eq!((elf, line), (1, 5));
+
+        let input = format!("1\n\n{}\n1\n", u64::MAX);
+        assert_eq!(do_solve(&input), Err(ParseError::Overflow { elf: 1, line: 4 }));
+
+        let huge = u64::MAX / 2;
+        assert_eq!(
+            do_solve(&format!("{huge}\n\n{huge}\n\n{huge}\n")),
+            Err(ParseError::TopOverflow)
+        );
+    }
 
-    (biggest[0], biggest.into_iter().sum::<u64>())
+    #[test]
+    fn test_resume() {
+        let mut elves = Elves::new("1\n2\n\n3\nfour\n5\n\n6\n");
+        assert_eq!(elves.next(), Some(Ok(3)));
+        assert!(matches!(
+            elves.next(),
+            Some(Err(ParseError::BadLine { elf: 1, line: 5, .. }))
+        ));
+        assert_eq!(elves.next(), Some(Ok(6)));
+        assert_eq!(elves.next(), None);
+        assert_eq!(elves.elf, 3);
+    }
 }
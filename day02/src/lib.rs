@@ -1,32 +1,35 @@
-use std::{fmt::Display, str::FromStr};
+use std::{error::Error, fmt::Display, str::FromStr};
 
-#[derive(Clone, Copy)]
-enum Move {
-    Rock,
-    Paper,
-    Scissors,
-}
+use ahash::HashMap;
+
+/// Plain old Rock-Paper-Scissors, scored as in the puzzle.
+pub const STANDARD_RULES: &str = "\
+move Rock 1 A X
+move Paper 2 B Y
+move Scissors 3 C Z
+outcome lose 0 X
+outcome draw 3 Y
+outcome win 6 Z
+";
+
+/// A move, as an index into its rule set's cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(usize);
 
-#[derive(Clone, Copy)]
-enum Outcome {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
     Lose,
     Draw,
     Win,
 }
 
-use Move::*;
 use Outcome::*;
 
-impl FromStr for Move {
-    type Err = ();
+impl Outcome {
+    pub const ALL: [Outcome; 3] = [Lose, Draw, Win];
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "A" | "X" => Rock,
-            "B" | "Y" => Paper,
-            "C" | "Z" => Scissors,
-            _ => unreachable!(),
-        })
+    pub fn value(self, rules: &Rules) -> u64 {
+        rules.outcomes[self as usize]
     }
 }
 
@@ -35,76 +38,429 @@ impl FromStr for Outcome {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
-            "X" => Lose,
-            "Y" => Draw,
-            "Z" => Win,
-            _ => unreachable!(),
+            "lose" => Lose,
+            "draw" => Draw,
+            "win" => Win,
+            _ => return Err(()),
         })
     }
 }
 
-impl Outcome {
-    fn value(self) -> u64 {
+impl Move {
+    /// Moves are laid out in a cycle where each move beats the half of the cycle that comes right before it.
+    pub fn outcome(self, other: Move, rules: &Rules) -> Outcome {
+        let n = rules.moves.len();
+        match (self.0 + n - other.0) % n {
+            0 => Draw,
+            d if d <= n / 2 => Win,
+            _ => Lose,
+        }
+    }
+
+    pub fn value(self, rules: &Rules) -> u64 {
+        rules.moves[self.0].score
+    }
+
+    pub fn name(self, rules: &Rules) -> &str {
+        &rules.moves[self.0].name
+    }
+
+    /// The score we get by playing `other` against this move.
+    pub fn part1_score(self, other: Move, rules: &Rules) -> u64 {
+        other.value(rules) + other.outcome(self, rules).value(rules)
+    }
+
+    /// The score we get by playing so that the round against this move ends as `outcome`.
+    pub fn part2_score(self, outcome: Outcome, rules: &Rules) -> u64 {
+        outcome.value(rules) + rules.response(self, outcome).value(rules)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MoveRule {
+    name: String,
+    score: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RulesError {
+    /// A line that isn't a well-formed `move` or `outcome` record.
+    Syntax {
+        line: usize,
+    },
+    /// A game needs at least three moves for every move to be able to win and lose.
+    TooFewMoves(usize),
+    /// A game needs an odd number of moves for every pair of moves to have a winner.
+    EvenMoveCount(usize),
+    MissingOutcome(Outcome),
+    DuplicateCode(String),
+}
+
+impl Display for RulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Win => 6,
-            Lose => 0,
-            Draw => 3,
+            RulesError::Syntax { line } => write!(f, "line {line}: expected a move or outcome record"),
+            RulesError::TooFewMoves(n) => write!(f, "a cyclic game needs at least 3 moves, got {n}"),
+            RulesError::EvenMoveCount(n) => write!(f, "a cyclic game needs an odd number of moves, got {n}"),
+            RulesError::MissingOutcome(outcome) => write!(f, "no score given for outcome {outcome:?}"),
+            RulesError::DuplicateCode(code) => write!(f, "code {code:?} is used more than once"),
         }
     }
 }
 
-impl Move {
-    fn outcome(self, other: Move) -> Outcome {
-        match (self, other) {
-            (Rock, Rock) => Draw,
-            (Rock, Paper) => Lose,
-            (Rock, Scissors) => Win,
-            (Paper, Rock) => Win,
-            (Paper, Paper) => Draw,
-            (Paper, Scissors) => Lose,
-            (Scissors, Rock) => Lose,
-            (Scissors, Paper) => Win,
-            (Scissors, Scissors) => Draw,
+impl Error for RulesError {}
+
+/// A cyclic game's moves, their scores and the letter codes the strategy guide uses for them.
+///
+/// Rules are written one record per line, with moves listed in cycle order:
+///
+/// ```text
+/// move <name> <score> <opponent code> <player code>
+/// outcome <lose|draw|win> <score> <code>
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rules {
+    moves: Vec<MoveRule>,
+    /// The score for each outcome, indexed by [`Outcome`].
+    outcomes: [u64; 3],
+    opponent_codes: HashMap<String, Move>,
+    player_codes: HashMap<String, Move>,
+    outcome_codes: HashMap<String, Outcome>,
+    /// The move to play against each move for each outcome; see [`Rules::response`].
+    responses: Vec<[Move; 3]>,
+}
+
+impl FromStr for Rules {
+    type Err = RulesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut moves = Vec::new();
+        let mut outcomes = [None; 3];
+        let mut opponent_codes = HashMap::default();
+        let mut player_codes = HashMap::default();
+        let mut outcome_codes = HashMap::default();
+        // Every code in a column has to be different.
+        let unique = |replaced: bool, code: &str| {
+            if replaced {
+                Err(RulesError::DuplicateCode(code.to_owned()))
+            } else {
+                Ok(())
+            }
+        };
+
+        for (idx, line) in s.lines().enumerate() {
+            let syntax = || RulesError::Syntax { line: idx + 1 };
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match fields[..] {
+                [] => {}
+                ["move", name, score, opponent_code, player_code] => {
+                    let mv = Move(moves.len());
+                    moves.push(MoveRule {
+                        name: name.to_owned(),
+                        score: score.parse().map_err(|_| syntax())?,
+                    });
+                    unique(
+                        opponent_codes.insert(opponent_code.to_owned(), mv).is_some(),
+                        opponent_code,
+                    )?;
+                    unique(player_codes.insert(player_code.to_owned(), mv).is_some(), player_code)?;
+                }
+                ["outcome", outcome, score, code] => {
+                    let outcome = outcome.parse::<Outcome>().map_err(|_| syntax())?;
+                    outcomes[outcome as usize] = Some(score.parse().map_err(|_| syntax())?);
+                    unique(outcome_codes.insert(code.to_owned(), outcome).is_some(), code)?;
+                }
+                _ => return Err(syntax()),
+            }
+        }
+
+        if moves.len() < 3 {
+            return Err(RulesError::TooFewMoves(moves.len()));
+        }
+        if moves.len() % 2 == 0 {
+            return Err(RulesError::EvenMoveCount(moves.len()));
         }
+
+        let [lose, draw, win] = outcomes;
+        let mut rules = Self {
+            moves,
+            outcomes: [
+                lose.ok_or(RulesError::MissingOutcome(Lose))?,
+                draw.ok_or(RulesError::MissingOutcome(Draw))?,
+                win.ok_or(RulesError::MissingOutcome(Win))?,
+            ],
+            opponent_codes,
+            player_codes,
+            outcome_codes,
+            responses: Vec::new(),
+        };
+        rules.responses = rules
+            .moves()
+            .map(|opponent| {
+                Outcome::ALL.map(|outcome| {
+                    // There are always moves that win, lose and draw against any move, since there are at least
+                    // three of them.
+                    rules
+                        .moves()
+                        .filter(|m| m.outcome(opponent, &rules) == outcome)
+                        .max_by_key(|m| m.value(&rules))
+                        .unwrap()
+                })
+            })
+            .collect();
+        Ok(rules)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        STANDARD_RULES.parse().unwrap()
+    }
+}
+
+impl Rules {
+    pub fn moves(&self) -> impl Iterator<Item = Move> {
+        (0..self.moves.len()).map(Move)
+    }
+
+    pub fn opponent_move(&self, code: &str) -> Option<Move> {
+        self.opponent_codes.get(code).copied()
+    }
+
+    pub fn player_move(&self, code: &str) -> Option<Move> {
+        self.player_codes.get(code).copied()
     }
 
-    fn value(self) -> u64 {
+    pub fn outcome(&self, code: &str) -> Option<Outcome> {
+        self.outcome_codes.get(code).copied()
+    }
+
+    /// The move to play against `opponent` so that the round ends as `outcome`.
+    ///
+    /// When more than one move would do, the highest-scoring one is picked.
+    pub fn response(&self, opponent: Move, outcome: Outcome) -> Move {
+        self.responses[opponent.0][outcome as usize]
+    }
+
+    /// The move that scores the most against `opponent`.
+    pub fn best_response(&self, opponent: Move) -> Move {
+        self.moves().max_by_key(|&m| opponent.part1_score(m, self)).unwrap()
+    }
+}
+
+/// How a series of rounds went, from our point of view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub score: u64,
+}
+
+impl Tally {
+    /// Record `rounds` rounds where we play `ours` against `opponent`.
+    fn record(&mut self, opponent: Move, ours: Move, rounds: usize, rules: &Rules) {
+        match ours.outcome(opponent, rules) {
+            Win => self.wins += rounds,
+            Draw => self.draws += rounds,
+            Lose => self.losses += rounds,
+        }
+        self.score += opponent.part1_score(ours, rules) * rounds as u64;
+    }
+}
+
+impl Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>6} {:>6} {:>6} {:>8}",
+            self.wins, self.draws, self.losses, self.score
+        )
+    }
+}
+
+/// The best possible tally against a sequence of opponent moves, playing the best response every round.
+pub fn optimise(rules: &Rules, opponents: impl IntoIterator<Item = Move>) -> Tally {
+    let mut rounds = vec![0; rules.moves.len()];
+    opponents.into_iter().for_each(|opponent| rounds[opponent.0] += 1);
+    optimise_rounds(rules, &rounds)
+}
+
+/// The best possible tally given how many rounds each move is played by the opponent.
+fn optimise_rounds(rules: &Rules, rounds: &[usize]) -> Tally {
+    let mut tally = Tally::default();
+    for (opponent, &rounds) in rules.moves().zip(rounds) {
+        tally.record(opponent, rules.best_response(opponent), rounds, rules);
+    }
+    tally
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuideError {
+    /// A line that isn't two codes separated by a space.
+    Syntax { line: usize },
+    /// A code the rules don't know, in the column it's used in.
+    UnknownCode { line: usize, code: String },
+}
+
+impl Display for GuideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Rock => 1,
-            Paper => 2,
-            Scissors => 3,
+            GuideError::Syntax { line } => write!(f, "line {line}: expected two codes separated by a space"),
+            GuideError::UnknownCode { line, code } => write!(f, "line {line}: unknown code {code:?}"),
         }
     }
+}
 
-    fn part1_score(self, other: Move) -> u64 {
-        other.value() + other.outcome(self).value()
+impl Error for GuideError {}
+
+/// A strategy guide scored under both readings of its second column, next to the best achievable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The second column read as the move to play.
+    pub as_moves: Tally,
+    /// The second column read as the outcome to aim for.
+    pub as_outcomes: Tally,
+    pub best: Tally,
+}
+
+impl Report {
+    /// Line numbers in errors start at one.
+    pub fn analyse(rules: &Rules, guide: &str) -> Result<Self, GuideError> {
+        // Guides only use a handful of different lines, so score each one once, along with where it first appears
+        // for errors and how many times it's used.
+        let mut lines = HashMap::<&str, (usize, usize)>::default();
+        for (idx, guide_line) in guide.lines().enumerate() {
+            lines.entry(guide_line).or_insert((idx + 1, 0)).1 += 1;
+        }
+        let mut lines = lines.into_iter().collect::<Vec<_>>();
+        lines.sort_unstable_by_key(|&(_, (line, _))| line);
+
+        let mut as_moves = Tally::default();
+        let mut as_outcomes = Tally::default();
+        let mut opponents = vec![0; rules.moves.len()];
+        for (guide_line, (line, rounds)) in lines {
+            let unknown = |code: &str| GuideError::UnknownCode {
+                line,
+                code: code.to_owned(),
+            };
+            let (left, right) = guide_line.split_once(' ').ok_or(GuideError::Syntax { line })?;
+            let left = rules.opponent_move(left).ok_or_else(|| unknown(left))?;
+            let ours = rules.player_move(right).ok_or_else(|| unknown(right))?;
+            let outcome = rules.outcome(right).ok_or_else(|| unknown(right))?;
+            as_moves.record(left, ours, rounds, rules);
+            as_outcomes.record(left, rules.response(left, outcome), rounds, rules);
+            opponents[left.0] += rounds;
+        }
+
+        Ok(Self {
+            as_moves,
+            as_outcomes,
+            best: optimise_rounds(rules, &opponents),
+        })
     }
+}
 
-    fn part2_score(self, outcome: Outcome) -> u64 {
-        outcome.value()
-            + (match (self, outcome) {
-                (_, Draw) => self,
-                (Rock, Lose) => Scissors,
-                (Rock, Win) => Paper,
-                (Paper, Lose) => Rock,
-                (Paper, Win) => Scissors,
-                (Scissors, Lose) => Paper,
-                (Scissors, Win) => Rock,
-            })
-            .value()
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>6} {:>6} {:>6} {:>8} {:>8}",
+            "", "wins", "draws", "losses", "score", "missed"
+        )?;
+        for (name, tally) in [
+            ("as moves", self.as_moves),
+            ("as outcomes", self.as_outcomes),
+            ("best", self.best),
+        ] {
+            writeln!(f, "{name:<12} {tally} {:>8}", self.best.score - tally.score)?;
+        }
+        Ok(())
     }
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    let mut p1 = 0_u64;
-    let mut p2 = 0_u64;
-    include_str!("input.txt").lines().for_each(|line| {
-        let (left, right) = line.split_once(' ').unwrap();
-        let left = left.parse::<Move>().unwrap();
-        p1 += left.part1_score(right.parse().unwrap());
-        p2 += left.part2_score(right.parse().unwrap());
-    });
-
-    (p1, p2)
+    let report = Report::analyse(&Rules::default(), include_str!("input.txt")).unwrap();
+    (report.as_moves.score, report.as_outcomes.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard() {
+        let report = Report::analyse(&Rules::default(), "A Y\nB X\nC Z\n").unwrap();
+        assert_eq!((report.as_moves.score, report.as_outcomes.score), (15, 12));
+        assert_eq!(
+            report.as_moves,
+            Tally {
+                wins: 1,
+                draws: 1,
+                losses: 1,
+                score: 15
+            }
+        );
+        // Paper against Rock, Scissors against Paper and Rock against Scissors all win.
+        assert_eq!(report.best.wins, 3);
+        assert_eq!(report.best.score, 8 + 9 + 7);
+    }
+
+    #[test]
+    fn test_lizard_spock() {
+        let rules: Rules = "\
+move Rock 1 A V
+move Spock 5 B W
+move Paper 2 C X
+move Lizard 4 D Y
+move Scissors 3 E Z
+outcome lose 0 X
+outcome draw 3 Y
+outcome win 6 Z
+"
+        .parse()
+        .unwrap();
+        let [rock, spock, paper, lizard, scissors] = ["V", "W", "X", "Y", "Z"].map(|c| rules.player_move(c).unwrap());
+
+        assert_eq!(spock.outcome(scissors, &rules), Win);
+        assert_eq!(lizard.outcome(spock, &rules), Win);
+        assert_eq!(rock.outcome(paper, &rules), Lose);
+        assert_eq!(paper.outcome(paper, &rules), Draw);
+        // Both Spock and Paper beat Rock; Spock scores more.
+        assert_eq!(rules.response(rock, Win), spock);
+    }
+
+    #[test]
+    fn test_bad_rules() {
+        assert_eq!(
+            "move Rock 1 A W\nmove Paper 2 B X\nmove Scissors 3 C Y\nmove Well 4 D Z\n"
+                .parse::<Rules>()
+                .unwrap_err(),
+            RulesError::EvenMoveCount(4)
+        );
+        assert_eq!(
+            STANDARD_RULES.replace("C Z", "C Y").parse::<Rules>().unwrap_err(),
+            RulesError::DuplicateCode("Y".to_owned())
+        );
+        assert_eq!(
+            "move Rock 1 A X\noutcome lose 0 X\noutcome draw 3 Y\noutcome win 6 Z\n"
+                .parse::<Rules>()
+                .unwrap_err(),
+            RulesError::TooFewMoves(1)
+        );
+    }
+
+    #[test]
+    fn test_bad_guide() {
+        let rules = Rules::default();
+        assert_eq!(Report::analyse(&rules, "A Y\nB\n"), Err(GuideError::Syntax { line: 2 }));
+        assert_eq!(
+            Report::analyse(&rules, "A Y\nB X\nD Z\n"),
+            Err(GuideError::UnknownCode {
+                line: 3,
+                code: "D".to_owned()
+            })
+        );
+    }
 }
@@ -1,38 +1,423 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Sub},
+};
 
-use std::fmt::Display;
+pub mod simd;
 
-fn parse_compartment(s: &str) -> u64 {
-    let mut result = 0;
-    s.bytes().for_each(|b| {
-        debug_assert!(b.is_ascii_alphabetic());
-        result |= if b.is_ascii_lowercase() {
-            1 << (b - b'a')
-        } else {
-            1 << (b - b'A' + 26)
+/// A set of rucksack items, stored as one bit per priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ItemSet(u64);
+
+impl ItemSet {
+    pub const EMPTY: Self = Self(0);
+    pub const ALL: Self = Self((1 << 52) - 1);
+
+    /// The priority of an item: `a` through `z` are 1 through 26, `A` through `Z` are 27 through 52.
+    pub fn priority(item: u8) -> Option<u32> {
+        match item {
+            b'a'..=b'z' => Some((item - b'a') as u32 + 1),
+            b'A'..=b'Z' => Some((item - b'A') as u32 + 27),
+            _ => None,
+        }
+    }
+
+    /// The item with a given priority.
+    pub fn item(priority: u32) -> Option<u8> {
+        match priority {
+            1..=26 => Some(b'a' + (priority - 1) as u8),
+            27..=52 => Some(b'A' + (priority - 27) as u8),
+            _ => None,
+        }
+    }
+
+    /// Build a set out of a compartment's contents, which should only be letters.
+    pub fn from_items(items: &[u8]) -> Self {
+        let mut result = Self::EMPTY;
+        items.iter().for_each(|&b| {
+            debug_assert!(b.is_ascii_alphabetic());
+            result.insert(b);
+        });
+        result
+    }
+
+    /// Build a set out of a compartment's contents, or give the position of the first thing in it that isn't a
+    /// letter.
+    pub fn try_from_items(items: &[u8]) -> Result<Self, usize> {
+        let mut result = Self::EMPTY;
+        for (idx, &b) in items.iter().enumerate() {
+            let priority = Self::priority(b).ok_or(idx)?;
+            result.0 |= 1 << (priority - 1);
+        }
+        Ok(result)
+    }
+
+    /// Add an item to the set, returning whether it was new. Anything that isn't a letter is left out.
+    pub fn insert(&mut self, item: u8) -> bool {
+        let Some(priority) = Self::priority(item) else {
+            return false;
+        };
+        let bit = 1 << (priority - 1);
+        let new = self.0 & bit == 0;
+        self.0 |= bit;
+        new
+    }
+
+    pub fn contains(self, item: u8) -> bool {
+        Self::priority(item).is_some_and(|p| self.0 & (1 << (p - 1)) != 0)
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The items in the set, in priority order.
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            let priority = bits.trailing_zeros() + 1;
+            bits &= bits.checked_sub(1)?;
+            Self::item(priority)
+        })
+    }
+
+    pub fn priority_sum(self) -> u32 {
+        self.iter().map(|item| Self::priority(item).unwrap()).sum()
+    }
+}
+
+impl BitAnd for ItemSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl BitAndAssign for ItemSet {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl BitOr for ItemSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for ItemSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl Sub for ItemSet {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.difference(rhs)
+    }
+}
+
+/// Anything that isn't a letter is left out.
+impl FromIterator<u8> for ItemSet {
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        let mut result = Self::EMPTY;
+        iter.into_iter().for_each(|item| {
+            result.insert(item);
+        });
+        result
+    }
+}
+
+impl Display for ItemSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.iter().try_for_each(|item| write!(f, "{}", item as char))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditError {
+    /// Rucksacks need at least one compartment.
+    NoCompartments,
+    /// A rucksack with nothing in it, like a stray blank line.
+    EmptyRucksack { line: Option<usize> },
+    /// A rucksack whose items can't be split evenly between the compartments.
+    UnevenRucksack { line: Option<usize>, compartments: usize },
+    /// Something other than a letter.
+    BadItem { line: Option<usize>, item: char },
+    /// The elves can't be split into groups of the given size.
+    UnevenGroups { elves: usize, group_size: usize },
+}
+
+impl AuditError {
+    /// Fill in the line number of an error in a rucksack.
+    fn at(self, line_no: usize) -> Self {
+        let line = Some(line_no);
+        match self {
+            AuditError::EmptyRucksack { .. } => AuditError::EmptyRucksack { line },
+            AuditError::UnevenRucksack { compartments, .. } => AuditError::UnevenRucksack { line, compartments },
+            AuditError::BadItem { item, .. } => AuditError::BadItem { line, item },
+            err => err,
+        }
+    }
+}
+
+impl Display for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = |f: &mut std::fmt::Formatter<'_>, line: &Option<usize>| match line {
+            Some(line) => write!(f, "line {line}: "),
+            None => Ok(()),
         };
-    });
-    result
+        match self {
+            AuditError::NoCompartments => write!(f, "rucksacks need at least one compartment"),
+            AuditError::EmptyRucksack { line: l } => {
+                line(f, l)?;
+                write!(f, "empty rucksack")
+            }
+            AuditError::UnevenRucksack { line: l, compartments } => {
+                line(f, l)?;
+                write!(f, "rucksack can't be split evenly into {compartments} compartments")
+            }
+            AuditError::BadItem { line: l, item } => {
+                line(f, l)?;
+                write!(f, "{item:?} isn't an item")
+            }
+            AuditError::UnevenGroups { elves, group_size } => {
+                write!(f, "{elves} elves can't be split into groups of {group_size}")
+            }
+        }
+    }
+}
+
+impl Error for AuditError {}
+
+/// Splits a rucksack into the items every compartment shares and everything in it; see [`parse_rucksack`].
+pub type RucksackParser = fn(&str, usize) -> Result<(ItemSet, ItemSet), AuditError>;
+
+fn split_rucksack(
+    s: &str,
+    compartments: usize,
+    try_from_items: impl Fn(&[u8]) -> Result<ItemSet, usize>,
+) -> Result<(ItemSet, ItemSet), AuditError> {
+    if compartments == 0 {
+        return Err(AuditError::NoCompartments);
+    }
+    if s.is_empty() {
+        return Err(AuditError::EmptyRucksack { line: None });
+    }
+    if !s.len().is_multiple_of(compartments) {
+        return Err(AuditError::UnevenRucksack {
+            line: None,
+            compartments,
+        });
+    }
+
+    let size = s.len() / compartments;
+    let mut shared = ItemSet::ALL;
+    let mut all = ItemSet::EMPTY;
+    for (idx, compartment) in s.as_bytes().chunks(size).enumerate() {
+        let compartment = try_from_items(compartment).map_err(|pos| {
+            // Compartments are checked in order, so this is the first byte of the character that isn't a letter.
+            let item = s[idx * size + pos..].chars().next().unwrap();
+            AuditError::BadItem { line: None, item }
+        })?;
+        shared &= compartment;
+        all |= compartment;
+    }
+    Ok((shared, all))
+}
+
+/// Split a rucksack into equally-sized compartments, returning the items every compartment shares along
+/// with everything in the rucksack.
+pub fn parse_rucksack(s: &str, compartments: usize) -> Result<(ItemSet, ItemSet), AuditError> {
+    split_rucksack(s, compartments, ItemSet::try_from_items)
 }
 
-fn parse_rucksack(s: &str) -> (u64, u64) {
-    let (first, second) = s.split_at(s.len() / 2);
-    (parse_compartment(first), parse_compartment(second))
+/// The items found in every compartment of each rucksack and the badges shared by each group of elves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audit {
+    pub misplaced: Vec<ItemSet>,
+    pub badges: Vec<ItemSet>,
+}
+
+impl Audit {
+    /// Line numbers in errors start at one.
+    pub fn new(input: &str, compartments: usize, group_size: usize) -> Result<Self, AuditError> {
+        Self::with_parser(input, compartments, group_size, parse_rucksack)
+    }
+
+    /// Audit the rucksacks using a different rucksack parser, such as [`simd::rucksack_parser`].
+    pub fn with_parser(
+        input: &str,
+        compartments: usize,
+        group_size: usize,
+        parse_rucksack: RucksackParser,
+    ) -> Result<Self, AuditError> {
+        let elves = input
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| parse_rucksack(line, compartments).map_err(|err| err.at(idx + 1)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if group_size == 0 || !elves.len().is_multiple_of(group_size) {
+            return Err(AuditError::UnevenGroups {
+                elves: elves.len(),
+                group_size,
+            });
+        }
+
+        Ok(Self {
+            misplaced: elves.iter().map(|&(shared, _)| shared).collect(),
+            badges: elves
+                .chunks(group_size)
+                .map(|group| group.iter().fold(ItemSet::ALL, |acc, &(_, all)| acc & all))
+                .collect(),
+        })
+    }
+
+    pub fn misplaced_priority(&self) -> u32 {
+        self.misplaced.iter().map(|set| set.priority_sum()).sum()
+    }
+
+    pub fn badge_priority(&self) -> u32 {
+        self.badges.iter().map(|set| set.priority_sum()).sum()
+    }
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    let elves = include_str!("input.txt").lines().map(parse_rucksack).collect::<Vec<_>>();
-    let mut p1 = 0;
-    let mut p2 = 0;
+    let audit = Audit::new(include_str!("input.txt"), 2, 3).unwrap();
+    (audit.misplaced_priority(), audit.badge_priority())
+}
 
-    for group in elves.chunks(3) {
-        let group: [_; 3] = group.try_into().unwrap();
-        for (l, r) in group {
-            p1 += (l & r).trailing_zeros() + 1;
-        }
-        let group = group.map(|(l, r)| l | r);
-        p2 += (group[0] & group[1] & group[2]).trailing_zeros() + 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_set() {
+        let set = "vJrwpWtwJgWr".bytes().collect::<ItemSet>();
+        assert_eq!(set.to_string(), "gprtvwJW");
+        assert!(set.contains(b'J') && !set.contains(b'j'));
+        assert_eq!(set.len(), 8);
+        assert_eq!((set & "hcsFMMfFFhFp".bytes().collect()).to_string(), "p");
+        assert_eq!(ItemSet::priority(b'L'), Some(38));
+        assert_eq!(ItemSet::item(38), Some(b'L'));
+        assert_eq!(ItemSet::ALL.len(), 52);
+    }
+
+    #[test]
+    fn test_audit() {
+        let input = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw
+";
+        let audit = Audit::new(input, 2, 3).unwrap();
+        assert_eq!(
+            audit.misplaced.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            ["p", "L", "P", "v", "t", "s"]
+        );
+        assert_eq!(
+            audit.badges.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            ["r", "Z"]
+        );
+        assert_eq!((audit.misplaced_priority(), audit.badge_priority()), (157, 70));
+
+        let audit = Audit::new(input, 1, 6).unwrap();
+        assert_eq!(audit.badges, [ItemSet::EMPTY]);
+    }
+
+    #[test]
+    fn test_audit_errors() {
+        assert_eq!(
+            Audit::new("abAB\n\ncdCD\n", 2, 1),
+            Err(AuditError::EmptyRucksack { line: Some(2) })
+        );
+        assert_eq!(Audit::new("abAB\n", 0, 1), Err(AuditError::NoCompartments));
+        assert_eq!(
+            Audit::new("abAB\nabc\n", 2, 1),
+            Err(AuditError::UnevenRucksack {
+                line: Some(2),
+                compartments: 2
+            })
+        );
+        assert_eq!(
+            Audit::with_parser("ab1B\n", 2, 1, simd::rucksack_parser()),
+            Err(AuditError::BadItem {
+                line: Some(1),
+                item: '1'
+            })
+        );
+        assert_eq!(
+            Audit::new("abAB\nabcdéfg\n", 2, 1),
+            Err(AuditError::BadItem {
+                line: Some(2),
+                item: 'é'
+            })
+        );
+        assert_eq!(
+            Audit::new("abAB\n", 2, 0),
+            Err(AuditError::UnevenGroups {
+                elves: 1,
+                group_size: 0
+            })
+        );
+
+        let mut set = ItemSet::EMPTY;
+        assert!(!set.insert(b'!'));
+        assert_eq!("a-b".bytes().collect::<ItemSet>().to_string(), "ab");
     }
 
-    (p1, p2)
+    #[test]
+    fn test_simd_matches_scalar() {
+        // Every letter in every position, with lengths that exercise both the vector loop and its remainder.
+        let letters = (b'a'..=b'z').chain(b'A'..=b'Z').collect::<Vec<_>>();
+        for len in 0..=64 {
+            for offset in 0..letters.len() {
+                let items = letters
+                    .iter()
+                    .cycle()
+                    .skip(offset)
+                    .step_by(7)
+                    .take(len)
+                    .copied()
+                    .collect::<Vec<_>>();
+                assert_eq!(simd::from_items(&items), Ok(ItemSet::from_items(&items)), "{items:?}");
+            }
+        }
+
+        // Anything that isn't a letter, wherever it turns up.
+        let items = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLM".to_vec();
+        for bad in [b'@', b'[', b'`', b'{', b'0', b' ', 0x80, 0xc1, 0xff] {
+            for pos in 0..items.len() {
+                let mut items = items.clone();
+                items[pos] = bad;
+                assert_eq!(simd::from_items(&items), Err(pos), "{bad:#x} at {pos}");
+                assert_eq!(ItemSet::try_from_items(&items), Err(pos));
+            }
+        }
+    }
 }
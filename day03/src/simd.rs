@@ -0,0 +1,94 @@
+//! Vectorised rucksack parsing, for when there are a great many rucksacks to go through.
+use crate::{split_rucksack, AuditError, ItemSet, RucksackParser};
+
+/// Same as [`ItemSet::try_from_items`], using AVX2 if the CPU supports it.
+pub fn from_items(items: &[u8]) -> Result<ItemSet, usize> {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: We've just checked that AVX2 is available.
+        return unsafe { avx2::from_items(items) };
+    }
+
+    ItemSet::try_from_items(items)
+}
+
+/// A rucksack parser that builds each compartment with AVX2 if the CPU supports it, or [`crate::parse_rucksack`]
+/// if it doesn't. The CPU is only checked here, so hang on to the parser rather than asking for it every time.
+pub fn rucksack_parser() -> RucksackParser {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return parse_rucksack_avx2;
+    }
+
+    crate::parse_rucksack
+}
+
+/// Only ever handed out by [`rucksack_parser`] once it's made sure AVX2 is available.
+#[cfg(target_arch = "x86_64")]
+fn parse_rucksack_avx2(s: &str, compartments: usize) -> Result<(ItemSet, ItemSet), AuditError> {
+    // SAFETY: See above.
+    split_rucksack(s, compartments, |items| unsafe { avx2::from_items(items) })
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use crate::ItemSet;
+
+    const LANES: usize = 8;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn from_items(items: &[u8]) -> Result<ItemSet, usize> {
+        // Priorities go up to 52, so we keep the low and high 32 bits of the set in separate vectors: shifting
+        // a 32-bit lane by 32 or more (or by a "negative" amount) gives zero, which takes care of picking a half.
+        let ones = _mm256_set1_epi32(1);
+        let mut lo = _mm256_setzero_si256();
+        let mut hi = _mm256_setzero_si256();
+        // Lanes that have held something other than a letter.
+        let mut bad = _mm256_setzero_si256();
+
+        let mut chunks = items.chunks_exact(LANES);
+        for chunk in chunks.by_ref() {
+            let lanes = _mm256_cvtepu8_epi32(_mm_loadl_epi64(chunk.as_ptr().cast()));
+
+            // Setting bit 5 makes uppercase letters lowercase and leaves lowercase ones alone, so anything that
+            // ends up outside `a..=z` isn't a letter.
+            let folded = _mm256_or_si256(lanes, _mm256_set1_epi32(0x20));
+            let letter = _mm256_and_si256(
+                _mm256_cmpgt_epi32(folded, _mm256_set1_epi32(b'a' as i32 - 1)),
+                _mm256_cmpgt_epi32(_mm256_set1_epi32(b'z' as i32 + 1), folded),
+            );
+            bad = _mm256_or_si256(bad, _mm256_andnot_si256(letter, _mm256_set1_epi32(-1)));
+
+            // Uppercase letters map to `b - 'A' + 26`, lowercase ones to `b - 'a'`, which is 58 less than that.
+            let lower = _mm256_cmpgt_epi32(lanes, _mm256_set1_epi32(b'Z' as i32));
+            let priorities = _mm256_sub_epi32(
+                _mm256_sub_epi32(lanes, _mm256_set1_epi32(b'A' as i32 - 26)),
+                _mm256_and_si256(lower, _mm256_set1_epi32((b'a' - b'A' + 26) as i32)),
+            );
+
+            lo = _mm256_or_si256(lo, _mm256_sllv_epi32(ones, priorities));
+            hi = _mm256_or_si256(
+                hi,
+                _mm256_sllv_epi32(ones, _mm256_sub_epi32(priorities, _mm256_set1_epi32(32))),
+            );
+        }
+
+        if _mm256_testz_si256(bad, bad) == 0 {
+            // Something's wrong, so it's worth going back over the items one by one to see where.
+            return Err(items.iter().position(|b| !b.is_ascii_alphabetic()).unwrap());
+        }
+        let done = items.len() - chunks.remainder().len();
+        let rest = ItemSet::try_from_items(chunks.remainder()).map_err(|pos| done + pos)?;
+
+        let mut lo_lanes = [0u32; LANES];
+        let mut hi_lanes = [0u32; LANES];
+        _mm256_storeu_si256(lo_lanes.as_mut_ptr().cast(), lo);
+        _mm256_storeu_si256(hi_lanes.as_mut_ptr().cast(), hi);
+        let lo = lo_lanes.into_iter().fold(0, |acc, lane| acc | lane) as u64;
+        let hi = hi_lanes.into_iter().fold(0, |acc, lane| acc | lane) as u64;
+
+        Ok(ItemSet(lo | hi << 32) | rest)
+    }
+}
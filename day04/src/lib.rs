@@ -1,18 +1,188 @@
-use std::fmt::Display;
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display};
 
-type Pair = (u8, u8);
+mod range;
+pub use range::{ParseRangeError, Section, SectionRange};
 
-fn parse_range(r: &str) -> Pair {
-    let (s, e) = r.split_once('-').unwrap();
-    (s.parse().unwrap(), e.parse().unwrap())
+pub fn parse_pair<T: Section>(line: &str) -> Result<(SectionRange<T>, SectionRange<T>), ParseRangeError> {
+    let (l, r) = line.split_once(',').ok_or(ParseRangeError::MissingComma)?;
+    Ok((l.parse()?, r.parse()?))
 }
 
-fn contains(container: Pair, contained: Pair) -> bool {
-    container.0 <= contained.0 && container.1 >= contained.1
+/// An elf's assignment, along with where it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment<T> {
+    /// The line it's on, starting at one.
+    pub line: usize,
+    /// Which elf of the pair it belongs to, starting at zero.
+    pub elf: usize,
+    pub range: SectionRange<T>,
 }
 
-fn overlap(first: Pair, second: Pair) -> bool {
-    first.0 <= second.1 && first.1 >= second.0
+impl<T: Display> Display for Assignment<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} elf {} ({})", self.line, self.elf, self.range)
+    }
+}
+
+pub fn parse_assignments<T: Section>(input: &str) -> Result<Vec<Assignment<T>>, ParseRangeError> {
+    let mut assignments = Vec::new();
+    for (idx, line) in input.lines().enumerate() {
+        let (l, r) = parse_pair(line)?;
+        assignments.extend([l, r].into_iter().enumerate().map(|(elf, range)| Assignment {
+            line: idx + 1,
+            elf,
+            range,
+        }));
+    }
+    Ok(assignments)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// The ranges share some sections.
+    Overlaps,
+    /// One range contains the other.
+    Nests,
+}
+
+/// Two elves whose assignments share some sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clash<T> {
+    /// The assignment that starts first.
+    pub first: Assignment<T>,
+    pub second: Assignment<T>,
+    pub relation: Relation,
+}
+
+impl<T: Display> Display for Clash<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verb = match self.relation {
+            Relation::Overlaps => "overlaps",
+            Relation::Nests => "nests with",
+        };
+        write!(f, "{} {verb} {}", self.first, self.second)
+    }
+}
+
+/// Every pair of assignments in the file that overlap, whether or not they're on the same line.
+///
+/// This sweeps over the assignments by start, keeping the ones still open in a heap keyed by their end, so it
+/// takes O(n log n) time on top of the time it takes to list the clashes.
+pub fn clashes<T: Section>(assignments: &[Assignment<T>]) -> Vec<Clash<T>> {
+    let mut order = (0..assignments.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| assignments[idx].range.start());
+
+    let mut open: BinaryHeap<Reverse<(T, usize)>> = BinaryHeap::new();
+    let mut clashes = Vec::new();
+    for idx in order {
+        let second = assignments[idx];
+        while let Some(&Reverse((end, _))) = open.peek() {
+            if end >= second.range.start() {
+                break;
+            }
+            open.pop();
+        }
+
+        // Everything still open started no later than this one and hasn't ended yet, so it overlaps.
+        clashes.extend(open.iter().map(|&Reverse((_, other))| {
+            let first = assignments[other];
+            debug_assert!(first.range.overlaps(second.range));
+            let relation = if first.range.contains(second.range) || second.range.contains(first.range) {
+                Relation::Nests
+            } else {
+                Relation::Overlaps
+            };
+            Clash {
+                first,
+                second,
+                relation,
+            }
+        }));
+
+        open.push(Reverse((second.range.end(), idx)));
+    }
+    clashes
+}
+
+/// How many elves are assigned to each stretch of sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage<T> {
+    /// Maximal runs of sections covered by the same (non-zero) number of elves, in order.
+    segments: Vec<(SectionRange<T>, usize)>,
+}
+
+impl<T: Section> Coverage<T> {
+    pub fn new(assignments: &[SectionRange<T>]) -> Self {
+        // Sweep over the points where an assignment starts or where one has just ended.
+        let mut events = Vec::with_capacity(2 * assignments.len());
+        for range in assignments {
+            events.push((range.start(), 1_isize));
+            if let Some(after) = range.end().succ() {
+                events.push((after, -1));
+            }
+        }
+        events.sort_unstable();
+
+        let mut segments: Vec<(SectionRange<T>, usize)> = Vec::new();
+        let mut depth = 0;
+        let mut events = events.into_iter().peekable();
+        while let Some((pos, delta)) = events.next() {
+            depth += delta;
+            while let Some((_, delta)) = events.next_if(|&(next, _)| next == pos) {
+                depth += delta;
+            }
+            if depth == 0 {
+                continue;
+            }
+
+            let end = events.peek().map_or(T::MAX, |&(next, _)| next.pred().unwrap());
+            let segment = SectionRange::new(pos, end).unwrap();
+            match segments.last_mut() {
+                Some((last, n)) if *n == depth as usize && last.end().succ() == Some(pos) => {
+                    *last = last.union(segment).unwrap()
+                }
+                _ => segments.push((segment, depth as usize)),
+            }
+        }
+
+        Self { segments }
+    }
+
+    pub fn segments(&self) -> &[(SectionRange<T>, usize)] {
+        &self.segments
+    }
+
+    /// How many sections at least one elf is assigned to.
+    pub fn total(&self) -> u64 {
+        self.segments.iter().map(|(range, _)| range.size()).sum()
+    }
+
+    /// How many sections exactly `k` elves are assigned to.
+    pub fn exactly(&self, k: usize) -> u64 {
+        self.segments
+            .iter()
+            .filter(|&&(_, n)| n == k)
+            .map(|(range, _)| range.size())
+            .sum()
+    }
+
+    /// The indices of the assignments whose every section is also assigned to some other elf.
+    pub fn redundant(&self, assignments: &[SectionRange<T>]) -> Vec<usize> {
+        assignments
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| {
+                let first = self
+                    .segments
+                    .partition_point(|(segment, _)| segment.end() < range.start());
+                self.segments[first..]
+                    .iter()
+                    .take_while(|(segment, _)| segment.start() <= range.end())
+                    .all(|&(_, n)| n >= 2)
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
 }
 
 #[inline]
@@ -22,18 +192,95 @@ pub fn solve() -> (impl Display, impl Display) {
 
     include_str!("input.txt")
         .lines()
-        .map(|line| {
-            let (l, r) = line.split_once(',').unwrap();
-            (parse_range(l), parse_range(r))
-        })
+        .map(|line| parse_pair::<u32>(line).unwrap())
         .for_each(|(l, r)| {
-            if contains(l, r) || contains(r, l) {
+            if l.contains(r) || r.contains(l) {
                 p1 += 1;
             }
-            if overlap(l, r) || overlap(r, l) {
+            if l.overlaps(r) {
                 p2 += 1;
             }
         });
 
     (p1, p2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(s: &str) -> SectionRange<u16> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_range_algebra() {
+        assert_eq!(range("2-6").intersection(range("4-8")), Some(range("4-6")));
+        assert_eq!(range("2-3").intersection(range("5-8")), None);
+        assert_eq!(range("2-3").union(range("4-8")), Some(range("2-8")));
+        assert_eq!(range("2-3").union(range("5-8")), None);
+        assert_eq!(
+            range("2-8").difference(range("4-5")).collect::<Vec<_>>(),
+            [range("2-3"), range("6-8")]
+        );
+        assert_eq!(range("4-5").difference(range("2-8")).count(), 0);
+        assert_eq!(range("300-1000").size(), 701);
+        assert_eq!("6-4".parse::<SectionRange<u8>>(), Err(ParseRangeError::Backwards));
+        assert_eq!("1-256".parse::<SectionRange<u8>>(), Err(ParseRangeError::BadSection));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let assignments = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8"
+            .lines()
+            .flat_map(|line| {
+                let (l, r) = parse_pair(line).unwrap();
+                [l, r]
+            })
+            .collect::<Vec<SectionRange<u16>>>();
+        let coverage = Coverage::new(&assignments);
+        assert_eq!(coverage.total(), 8);
+        assert_eq!((1..=assignments.len()).map(|k| coverage.exactly(k)).sum::<u64>(), 8);
+        assert_eq!(coverage.exactly(8), 1);
+        assert_eq!(coverage.exactly(1), 1);
+        // Everything but 7-9, which is the only assignment covering section 9.
+        assert_eq!(coverage.redundant(&assignments), [0, 1, 2, 3, 4, 6, 7, 8, 9, 10, 11]);
+
+        let coverage = Coverage::new(&[range("0-65535"), range("10-20")]);
+        assert_eq!(coverage.total(), 65536);
+        assert_eq!(coverage.exactly(2), 11);
+        assert_eq!(coverage.redundant(&[range("0-65535"), range("10-20")]), [1]);
+    }
+
+    #[test]
+    fn test_clashes() {
+        let assignments = parse_assignments::<u16>("2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8").unwrap();
+        let clashes = clashes(&assignments);
+
+        let mut expected = Vec::new();
+        for (i, a) in assignments.iter().enumerate() {
+            for b in &assignments[i + 1..] {
+                if a.range.overlaps(b.range) {
+                    expected.push([(a.line, a.elf), (b.line, b.elf)]);
+                }
+            }
+        }
+        let mut found = clashes
+            .iter()
+            .map(|clash| {
+                let mut pair = [clash.first, clash.second].map(|a| (a.line, a.elf));
+                pair.sort();
+                pair
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, expected);
+
+        let clash = clashes
+            .iter()
+            .find(|clash| (clash.first.line, clash.second.line) == (4, 4))
+            .unwrap();
+        assert_eq!(clash.relation, Relation::Nests);
+        assert_eq!(clash.to_string(), "line 4 elf 0 (2-8) nests with line 4 elf 1 (3-7)");
+    }
+}
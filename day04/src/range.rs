@@ -0,0 +1,135 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+/// A section ID: an unsigned integer we can step through one section at a time.
+pub trait Section: Copy + Ord + Debug + Display + FromStr {
+    const MAX: Self;
+
+    fn succ(self) -> Option<Self>;
+    fn pred(self) -> Option<Self>;
+    /// How many sections there are from `start` up to, but not including, `self`.
+    fn since(self, start: Self) -> u64;
+}
+
+macro_rules! impl_section {
+    ($($ty:ty),+) => {$(
+        impl Section for $ty {
+            const MAX: Self = <$ty>::MAX;
+
+            fn succ(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            fn pred(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+
+            fn since(self, start: Self) -> u64 {
+                (self - start) as u64
+            }
+        }
+    )+};
+}
+
+impl_section!(u8, u16, u32, u64, usize);
+
+/// An inclusive range of sections, like `2-4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SectionRange<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Section> SectionRange<T> {
+    /// Returns `None` if `start` comes after `end`.
+    pub fn new(start: T, end: T) -> Option<Self> {
+        (start <= end).then_some(Self { start, end })
+    }
+
+    pub fn start(self) -> T {
+        self.start
+    }
+
+    pub fn end(self) -> T {
+        self.end
+    }
+
+    /// How many sections are in the range.
+    pub fn size(self) -> u64 {
+        self.end.since(self.start) + 1
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.start <= other.start && self.end >= other.end
+    }
+
+    pub fn overlaps(self, other: Self) -> bool {
+        self.start <= other.end && self.end >= other.start
+    }
+
+    pub fn intersection(self, other: Self) -> Option<Self> {
+        Self::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// The range covering both ranges, as long as there's no gap between them.
+    pub fn union(self, other: Self) -> Option<Self> {
+        let adjacent = |a: Self, b: Self| a.end.succ() == Some(b.start);
+        (self.overlaps(other) || adjacent(self, other) || adjacent(other, self))
+            .then(|| Self::new(self.start.min(other.start), self.end.max(other.end)).unwrap())
+    }
+
+    /// The parts of this range not in `other`: none, one or two ranges.
+    pub fn difference(self, other: Self) -> impl Iterator<Item = Self> {
+        let (left, right) = if self.overlaps(other) {
+            (
+                other.start.pred().and_then(|end| Self::new(self.start, end)),
+                other.end.succ().and_then(|start| Self::new(start, self.end)),
+            )
+        } else {
+            (Some(self), None)
+        };
+        left.into_iter().chain(right)
+    }
+}
+
+impl<T: Display> Display for SectionRange<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseRangeError {
+    MissingDash,
+    MissingComma,
+    BadSection,
+    /// The range ends before it starts.
+    Backwards,
+}
+
+impl Display for ParseRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ParseRangeError::MissingDash => "expected a range like `2-4`",
+            ParseRangeError::MissingComma => "expected a pair of ranges like `2-4,6-8`",
+            ParseRangeError::BadSection => "invalid section ID",
+            ParseRangeError::Backwards => "range ends before it starts",
+        })
+    }
+}
+
+impl Error for ParseRangeError {}
+
+impl<T: Section> FromStr for SectionRange<T> {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or(ParseRangeError::MissingDash)?;
+        let start = start.parse().map_err(|_| ParseRangeError::BadSection)?;
+        let end = end.parse().map_err(|_| ParseRangeError::BadSection)?;
+        Self::new(start, end).ok_or(ParseRangeError::Backwards)
+    }
+}
@@ -0,0 +1,241 @@
+use std::{error::Error, fmt::Display, num::NonZeroUsize, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// Moves crates one at a time, so they end up in reverse order.
+    CrateMover9000,
+    /// Moves any number of crates at once, keeping their order.
+    CrateMover9001,
+    /// Moves up to `max_lift` crates at once, keeping their order within each lift.
+    Limited { max_lift: NonZeroUsize },
+}
+
+impl Model {
+    fn max_lift(self) -> usize {
+        match self {
+            Model::CrateMover9000 => 1,
+            Model::CrateMover9001 => usize::MAX,
+            Model::Limited { max_lift } => max_lift.get(),
+        }
+    }
+}
+
+/// Move `amount` crates from stack `from` to stack `to`, with stacks numbered from one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub amount: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseInstructionError;
+
+impl Display for ParseInstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("expected an instruction like `move 1 from 2 to 3`")
+    }
+}
+
+impl Error for ParseInstructionError {}
+
+impl FromStr for Instruction {
+    type Err = ParseInstructionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(' ');
+        let mut field = |name| {
+            (parts.next() == Some(name))
+                .then(|| parts.next()?.parse().ok())
+                .flatten()
+                .ok_or(ParseInstructionError)
+        };
+        let amount = field("move")?;
+        let from = field("from")?;
+        let to = field("to")?;
+        if parts.next().is_some() {
+            return Err(ParseInstructionError);
+        }
+        Ok(Self { amount, from, to })
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.amount, self.from, self.to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    NoSuchStack(usize),
+    NotEnoughCrates { stack: usize, wanted: usize, held: usize },
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoSuchStack(stack) => write!(f, "there is no stack {stack}"),
+            MoveError::NotEnoughCrates { stack, wanted, held } => {
+                write!(
+                    f,
+                    "can't move {wanted} crates from stack {stack}, which only holds {held}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// A crane working on some stacks of crates, which remembers what it did so that it can be undone.
+#[derive(Debug, Clone)]
+pub struct Crane<T = u8> {
+    model: Model,
+    stacks: Vec<Vec<T>>,
+    done: Vec<Instruction>,
+    undone: Vec<Instruction>,
+    buf: Vec<T>,
+}
+
+impl<T> Crane<T> {
+    /// Each stack is listed from the bottom up.
+    pub fn new(model: Model, stacks: Vec<Vec<T>>) -> Self {
+        Self {
+            model,
+            stacks,
+            done: Vec::new(),
+            undone: Vec::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn stacks(&self) -> &[Vec<T>] {
+        &self.stacks
+    }
+
+    /// The crate on top of each non-empty stack.
+    pub fn top_crates(&self) -> impl Iterator<Item = &T> {
+        self.stacks.iter().filter_map(|stack| stack.last())
+    }
+
+    fn check(&self, instruction: Instruction) -> Result<(), MoveError> {
+        for stack in [instruction.from, instruction.to] {
+            if !(1..=self.stacks.len()).contains(&stack) {
+                return Err(MoveError::NoSuchStack(stack));
+            }
+        }
+        let held = self.stacks[instruction.from - 1].len();
+        if instruction.amount > held {
+            return Err(MoveError::NotEnoughCrates {
+                stack: instruction.from,
+                wanted: instruction.amount,
+                held,
+            });
+        }
+        Ok(())
+    }
+
+    /// Carry out an instruction, forgetting anything that was undone before.
+    pub fn apply(&mut self, instruction: Instruction) -> Result<(), MoveError> {
+        self.check(instruction)?;
+        self.do_move(instruction);
+        self.done.push(instruction);
+        self.undone.clear();
+        Ok(())
+    }
+
+    fn do_move(&mut self, Instruction { amount, from, to }: Instruction) {
+        // The crane lifts crates off the top of the stack in batches, so the batch that was on top ends up at
+        // the bottom: flipping the whole lot and then flipping each batch back gets us there.
+        let offset = self.stacks[from - 1].len() - amount;
+        self.buf.extend(self.stacks[from - 1].drain(offset..));
+        self.buf.reverse();
+        self.buf
+            .chunks_mut(self.model.max_lift())
+            .for_each(|lift| lift.reverse());
+        self.stacks[to - 1].append(&mut self.buf);
+    }
+
+    /// Take back the last instruction, returning it.
+    pub fn undo(&mut self) -> Option<Instruction> {
+        let instruction @ Instruction { amount, from, to } = self.done.pop()?;
+        let offset = self.stacks[to - 1].len() - amount;
+        self.buf.extend(self.stacks[to - 1].drain(offset..));
+        self.buf.reverse();
+        self.buf
+            .rchunks_mut(self.model.max_lift())
+            .for_each(|lift| lift.reverse());
+        self.stacks[from - 1].append(&mut self.buf);
+        self.undone.push(instruction);
+        Some(instruction)
+    }
+
+    /// Carry out the last instruction that was undone again, returning it.
+    pub fn redo(&mut self) -> Option<Instruction> {
+        let instruction = self.undone.pop()?;
+        self.do_move(instruction);
+        self.done.push(instruction);
+        Some(instruction)
+    }
+
+    /// Work through a procedure lazily, so the stacks can be checked with [`Steps::crane`] after each instruction.
+    pub fn steps<I: IntoIterator<Item = Instruction>>(&mut self, instructions: I) -> Steps<'_, I::IntoIter, T> {
+        Steps {
+            crane: self,
+            instructions: instructions.into_iter(),
+            failed: false,
+        }
+    }
+
+    /// Carry out every instruction, stopping at the first one that can't be.
+    pub fn run(&mut self, instructions: impl IntoIterator<Item = Instruction>) -> Result<(), MoveError> {
+        self.steps(instructions).try_for_each(|step| step.map(|_| ()))
+    }
+}
+
+impl Crane<u8> {
+    pub fn tops(&self) -> String {
+        self.top_crates().map(|&b| b as char).collect()
+    }
+}
+
+impl Crane<String> {
+    pub fn tops(&self) -> String {
+        self.top_crates().map(String::as_str).collect()
+    }
+}
+
+/// An iterator working a crane through a rearrangement procedure one instruction at a time, yielding each
+/// instruction after its crates have been moved. An instruction naming a stack that isn't there, or asking for more
+/// crates than its stack has, is yielded as an error, and nothing after it is carried out.
+pub struct Steps<'a, I, T = u8> {
+    crane: &'a mut Crane<T>,
+    instructions: I,
+    failed: bool,
+}
+
+impl<I, T> Steps<'_, I, T> {
+    /// The stacks as the last instruction left them.
+    pub fn crane(&self) -> &Crane<T> {
+        self.crane
+    }
+}
+
+impl<I: Iterator<Item = Instruction>, T> Iterator for Steps<'_, I, T> {
+    type Item = Result<Instruction, MoveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let instruction = self.instructions.next()?;
+        let result = self.crane.apply(instruction).map(|()| instruction);
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
@@ -0,0 +1,240 @@
+use std::{error::Error, fmt::Display, iter, ops::Range, str::FromStr};
+
+use crate::{Crane, Instruction, Model, MoveError};
+
+/// A drawing of stacks of crates, like
+///
+/// ```text
+///     [D]
+/// [N] [C]
+/// [Z] [M] [P]
+///  1   2   3
+/// ```
+///
+/// Stacks are told apart by the labels on the last line: each crate belongs to the label right below it, so
+/// labels and crates can be any width as long as columns don't run into each other. A parsed drawing remembers
+/// where each stack's column was and how long its lines were, so that it's drawn back the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackDrawing {
+    labels: Vec<String>,
+    /// Where each stack's label and crates go, centred, as byte offsets into a line.
+    columns: Vec<Range<usize>>,
+    /// What every line is padded to with spaces.
+    width: usize,
+    stacks: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawingError {
+    /// The drawing is empty.
+    NoLabels,
+    /// A `[` without a matching `]`, or a pair of brackets with nothing in them.
+    BadCrate { line: usize, column: usize },
+    /// Something other than a crate on a crate line.
+    Junk { line: usize, column: usize },
+    /// A crate that isn't right above any label, or is above the same label as another crate on its line.
+    Unlabelled { line: usize, column: usize },
+    /// A crate with nothing under it.
+    Floating { line: usize, column: usize },
+}
+
+impl Display for DrawingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawingError::NoLabels => write!(f, "the drawing has no stack labels"),
+            DrawingError::BadCrate { line, column } => write!(f, "line {line}, column {column}: malformed crate"),
+            DrawingError::Junk { line, column } => write!(f, "line {line}, column {column}: expected a crate"),
+            DrawingError::Unlabelled { line, column } => {
+                write!(f, "line {line}, column {column}: crate isn't above exactly one label")
+            }
+            DrawingError::Floating { line, column } => {
+                write!(f, "line {line}, column {column}: crate has nothing under it")
+            }
+        }
+    }
+}
+
+impl Error for DrawingError {}
+
+/// The labels on a line, along with the byte offsets they start at.
+fn parse_labels(line: &str) -> Vec<(usize, &str)> {
+    let mut labels = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = line[pos..].find(|ch| ch != ' ') {
+        let start = pos + offset;
+        pos = line[start..].find(' ').map_or(line.len(), |len| start + len);
+        labels.push((start, &line[start..pos]));
+    }
+    labels
+}
+
+/// The crates on a line, brackets included, along with the byte offsets they start at.
+fn parse_crates(line: &str, line_no: usize) -> Result<Vec<(usize, &str)>, DrawingError> {
+    let mut crates = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = line[pos..].find(|ch| ch != ' ') {
+        let start = pos + offset;
+        let column = start + 1;
+        let Some(inner) = line[start..].strip_prefix('[') else {
+            return Err(DrawingError::Junk { line: line_no, column });
+        };
+        let len = inner
+            .find(']')
+            .filter(|&len| len != 0)
+            .ok_or(DrawingError::BadCrate { line: line_no, column })?;
+        pos = start + len + 2;
+        crates.push((start, &line[start..pos]));
+    }
+    Ok(crates)
+}
+
+impl FromStr for StackDrawing {
+    type Err = DrawingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<_>>();
+        let (label_line, crate_lines) = lines
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map(|idx| (lines[idx], &lines[..idx]))
+            .ok_or(DrawingError::NoLabels)?;
+        let labels = parse_labels(label_line);
+
+        let mut stacks = vec![Vec::new(); labels.len()];
+        let mut columns = labels
+            .iter()
+            .map(|&(start, label)| start..start + label.len())
+            .collect::<Vec<_>>();
+        // Go from the bottom up, so that we can push onto the stacks and notice floating crates.
+        for (idx, line) in crate_lines.iter().enumerate().rev() {
+            let line_no = idx + 1;
+            let level = crate_lines.len() - 1 - idx;
+            for (start, name) in parse_crates(line, line_no)? {
+                let column = start + 1;
+                let stack = labels
+                    .iter()
+                    .position(|&(label_start, label)| {
+                        label_start < start + name.len() && start < label_start + label.len()
+                    })
+                    .ok_or(DrawingError::Unlabelled { line: line_no, column })?;
+                let column = &mut columns[stack];
+                *column = column.start.min(start)..column.end.max(start + name.len());
+                let column = start + 1;
+                match stacks[stack].len() {
+                    len if len == level => stacks[stack].push(name[1..name.len() - 1].to_owned()),
+                    len if len > level => return Err(DrawingError::Unlabelled { line: line_no, column }),
+                    _ => return Err(DrawingError::Floating { line: line_no, column }),
+                }
+            }
+        }
+
+        Ok(Self {
+            labels: labels.into_iter().map(|(_, label)| label.to_owned()).collect(),
+            columns,
+            width: lines.iter().map(|line| line.len()).max().unwrap_or(0),
+            stacks,
+        })
+    }
+}
+
+impl Display for StackDrawing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Centre something in a column, rounding to the left, and after whatever's already on the line.
+        let place = |line: &mut String, column: &Range<usize>, content: &str| {
+            let offset = (column.len() as isize - content.len() as isize).div_euclid(2);
+            let mut start = column.start.saturating_add_signed(offset);
+            if !line.is_empty() {
+                start = start.max(line.len() + 1);
+            }
+            line.extend(iter::repeat_n(' ', start - line.len()));
+            line.push_str(content);
+        };
+
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        let mut lines = (0..height)
+            .rev()
+            .map(|y| {
+                let mut line = String::new();
+                for (stack, column) in self.stacks.iter().zip(&self.columns) {
+                    if let Some(name) = stack.get(y) {
+                        place(&mut line, column, &format!("[{name}]"));
+                    }
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+        let mut label_line = String::new();
+        for (label, column) in self.labels.iter().zip(&self.columns) {
+            place(&mut label_line, column, label);
+        }
+        lines.push(label_line);
+
+        let width = lines.iter().map(String::len).fold(self.width, usize::max);
+        lines.iter().try_for_each(|line| writeln!(f, "{line:width$}"))
+    }
+}
+
+impl StackDrawing {
+    /// Stacks are listed from the bottom up; there must be as many of them as there are labels. Each stack gets a
+    /// column wide enough for its label and crates, with a space between columns.
+    pub fn new(labels: Vec<String>, stacks: Vec<Vec<String>>) -> Self {
+        assert_eq!(labels.len(), stacks.len());
+        let mut columns = Vec::with_capacity(labels.len());
+        let mut start = 0;
+        for (label, stack) in labels.iter().zip(&stacks) {
+            let width = stack
+                .iter()
+                .map(|name| name.len() + 2)
+                .fold(label.len().max(3), usize::max);
+            columns.push(start..start + width);
+            start += width + 1;
+        }
+        Self {
+            labels,
+            columns,
+            width: start.saturating_sub(1),
+            stacks,
+        }
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn stacks(&self) -> &[Vec<String>] {
+        &self.stacks
+    }
+
+    /// A crane working on the stacks in this drawing.
+    pub fn crane(&self, model: Model) -> Crane<String> {
+        Crane::new(model, self.stacks.clone())
+    }
+
+    /// Carry out instructions one by one, yielding a drawing of the stacks after each one.
+    pub fn replay<'a>(
+        &'a self,
+        model: Model,
+        instructions: impl IntoIterator<Item = Instruction> + 'a,
+    ) -> impl Iterator<Item = Result<(Instruction, StackDrawing), MoveError>> + 'a {
+        let mut crane = self.crane(model);
+        let mut instructions = instructions.into_iter();
+        let mut failed = false;
+        iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let instruction = instructions.next()?;
+            if let Err(error) = crane.apply(instruction) {
+                failed = true;
+                return Some(Err(error));
+            }
+            Some(Ok((
+                instruction,
+                Self {
+                    stacks: crane.stacks().to_vec(),
+                    ..self.clone()
+                },
+            )))
+        })
+    }
+}
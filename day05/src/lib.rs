@@ -1,55 +1,167 @@
 use std::fmt::Display;
 
-fn do_solve(mut stacks: Vec<Vec<u8>>, instructions: &str, reverse: bool) -> String {
-    let mut buf = Vec::new();
-    for instruction in instructions.lines() {
-        let mut parts = instruction
-            .split(' ')
-            .skip(1)
-            .step_by(2)
-            .map(|n| n.parse::<usize>().unwrap());
-        let amount = parts.next().unwrap();
-        let from = parts.next().unwrap();
-        let to = parts.next().unwrap();
-        let offset = stacks[from - 1].len() - amount;
-        buf.clear();
-        buf.extend(stacks[from - 1].drain(offset..));
-        if reverse {
-            buf.reverse()
-        };
-        stacks[to - 1].append(&mut buf);
-    }
-    String::from_utf8(
-        stacks
-            .into_iter()
-            .map(|mut stack| stack.pop().unwrap())
-            .collect::<Vec<u8>>(),
-    )
-    .unwrap()
+mod crane;
+mod drawing;
+pub use crane::{Crane, Instruction, Model, MoveError, ParseInstructionError, Steps};
+pub use drawing::{DrawingError, StackDrawing};
+
+fn do_solve(drawing: &StackDrawing, instructions: &[Instruction], model: Model) -> String {
+    let mut crane = drawing.crane(model);
+    crane.run(instructions.iter().copied()).unwrap();
+    crane.tops()
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    let (initial_state, instructions) = include_str!("input.txt").split_once("\n\n").unwrap();
-    let mut stacks = vec![vec![]; (initial_state.find('\n').unwrap() + 3) / 4];
-
-    initial_state
+    let (drawing, instructions) = include_str!("input.txt").split_once("\n\n").unwrap();
+    let drawing = drawing.parse::<StackDrawing>().unwrap();
+    let instructions = instructions
         .lines()
-        .take_while(|row| row.as_bytes().get(1).map_or(false, |ch| !ch.is_ascii_digit()))
-        .for_each(|row| {
-            stacks
-                .iter_mut()
-                .zip(row.bytes().skip(1).step_by(4))
-                .for_each(|(stack, elem)| {
-                    if elem != b' ' {
-                        stack.push(elem)
-                    }
-                });
-        });
-    stacks.iter_mut().for_each(|stack| stack.reverse());
+        .map(|line| line.parse().unwrap())
+        .collect::<Vec<Instruction>>();
 
     (
-        do_solve(stacks.clone(), instructions, true),
-        do_solve(stacks, instructions, false),
+        do_solve(&drawing, &instructions, Model::CrateMover9000),
+        do_solve(&drawing, &instructions, Model::CrateMover9001),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+
+    const SAMPLE_DRAWING: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+
+    fn sample() -> (Vec<Vec<u8>>, Vec<Instruction>) {
+        let stacks = vec![b"ZN".to_vec(), b"MCD".to_vec(), b"P".to_vec()];
+        let instructions = "move 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2"
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        (stacks, instructions)
+    }
+
+    #[test]
+    fn test_models() {
+        let (_, instructions) = sample();
+        let drawing = SAMPLE_DRAWING.parse().unwrap();
+        assert_eq!(do_solve(&drawing, &instructions, Model::CrateMover9000), "CMZ");
+        assert_eq!(do_solve(&drawing, &instructions, Model::CrateMover9001), "MCD");
+
+        let mut crane = Crane::new(
+            Model::Limited {
+                max_lift: NonZeroUsize::new(2).unwrap(),
+            },
+            vec![b"ABCDE".to_vec(), vec![]],
+        );
+        crane.apply("move 5 from 1 to 2".parse().unwrap()).unwrap();
+        assert_eq!(crane.stacks()[1], b"DEBCA");
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let (stacks, instructions) = sample();
+        let mut crane = Crane::new(Model::CrateMover9000, stacks.clone());
+        let mut states = vec![crane.stacks().to_vec()];
+        let mut steps = crane.steps(instructions.iter().copied());
+        while let Some(step) = steps.next() {
+            step.unwrap();
+            states.push(steps.crane().stacks().to_vec());
+        }
+
+        while crane.undo().is_some() {
+            states.pop();
+            assert_eq!(crane.stacks(), states.last().unwrap());
+        }
+        assert_eq!(crane.stacks(), stacks);
+        crane.redo();
+        crane.redo();
+        assert_eq!(crane.tops(), "CZ");
+    }
+
+    #[test]
+    fn test_bad_moves() {
+        let (stacks, _) = sample();
+        let mut crane = Crane::new(Model::CrateMover9001, stacks);
+        assert_eq!(
+            crane.apply("move 2 from 3 to 1".parse().unwrap()),
+            Err(MoveError::NotEnoughCrates {
+                stack: 3,
+                wanted: 2,
+                held: 1
+            })
+        );
+        assert_eq!(
+            crane.apply("move 1 from 4 to 1".parse().unwrap()),
+            Err(MoveError::NoSuchStack(4))
+        );
+        assert_eq!("move 1 from 4".parse::<Instruction>(), Err(ParseInstructionError));
+        assert_eq!(
+            "move 1 from 2 to 3 junk".parse::<Instruction>(),
+            Err(ParseInstructionError)
+        );
+        assert_eq!(crane.tops(), "NDP");
+    }
+
+    #[test]
+    fn test_drawing_round_trip() {
+        let drawing = SAMPLE_DRAWING.parse::<StackDrawing>().unwrap();
+        assert_eq!(drawing.stacks(), [vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]);
+        assert_eq!(drawing.to_string(), SAMPLE_DRAWING);
+
+        // Written out line by line to keep the padding at the end of each one.
+        let wide = concat!(
+            "[A]                                 [K]         \n",
+            "[B] [C] [D] [E] [F] [G] [H] [I] [J] [L] [MN] [O]\n",
+            " 1   2   3   4   5   6   7   8   9  10   11  12 \n",
+        );
+        let drawing = wide.parse::<StackDrawing>().unwrap();
+        assert_eq!(drawing.labels().len(), 12);
+        assert_eq!(drawing.stacks()[9], ["L", "K"]);
+        assert_eq!(drawing.stacks()[10], ["MN"]);
+        assert_eq!(drawing.to_string(), wide);
+
+        // Drawings that weren't parsed get a column per stack, as wide as its label or widest crate.
+        let drawing = StackDrawing::new(
+            vec!["1".to_owned(), "10".to_owned()],
+            vec![vec!["MNOP".to_owned()], vec![]],
+        );
+        let rendered = drawing.to_string();
+        assert_eq!(rendered, "[MNOP]    \n  1    10 \n");
+        assert_eq!(rendered.parse::<StackDrawing>().unwrap().to_string(), rendered);
+    }
+
+    #[test]
+    fn test_replay() {
+        let (_, instructions) = sample();
+        let drawing = SAMPLE_DRAWING.parse::<StackDrawing>().unwrap();
+        let states = drawing
+            .replay(Model::CrateMover9001, instructions)
+            .map(|step| step.unwrap().1.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(states[0], "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n");
+        assert_eq!(states[3].parse::<StackDrawing>().unwrap().to_string(), states[3]);
+    }
+
+    #[test]
+    fn test_bad_drawings() {
+        assert_eq!(
+            "[A] [B\n 1   2 ".parse::<StackDrawing>(),
+            Err(DrawingError::BadCrate { line: 1, column: 5 })
+        );
+        assert_eq!(
+            "[A] B\n 1   2 ".parse::<StackDrawing>(),
+            Err(DrawingError::Junk { line: 1, column: 5 })
+        );
+        assert_eq!(
+            "[A]\n    [B]\n 1   2 ".parse::<StackDrawing>(),
+            Err(DrawingError::Floating { line: 1, column: 1 })
+        );
+        assert_eq!(
+            "        [A]\n 1   2 ".parse::<StackDrawing>(),
+            Err(DrawingError::Unlabelled { line: 1, column: 9 })
+        );
+    }
+}
@@ -1,36 +1,238 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    io::{self, Read},
+};
 
-fn first_marker(chars: usize) -> usize {
-    let bs = include_str!("input.txt").trim().as_bytes();
+const CHUNK_SIZE: usize = 8192;
+
+/// Ways of finding the first marker in a signal that's all in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Slide the window along one byte at a time, counting how many times each byte is in it.
+    Counting,
+    /// Check each window from its end with a bitmask, skipping past any duplicate found.
+    Bitmask,
+}
+
+/// How many bytes have to be read before the last `chars` bytes are all different.
+pub fn first_marker(bs: &[u8], chars: usize, algorithm: Algorithm) -> Option<usize> {
+    match algorithm {
+        Algorithm::Counting => first_marker_counting(bs, chars),
+        Algorithm::Bitmask => first_marker_bitmask(bs, chars),
+    }
+}
 
-    let mut seen = [0usize; 26];
+fn first_marker_counting(bs: &[u8], chars: usize) -> Option<usize> {
+    let mut seen = [0usize; 256];
     let mut unique = 0;
     bs.iter().take(chars).for_each(|&b| {
-        if seen[(b - b'a') as usize] == 0 {
+        if seen[b as usize] == 0 {
             unique += 1;
         }
-        seen[(b - b'a') as usize] += 1;
+        seen[b as usize] += 1;
     });
+    if bs.len() >= chars && unique == chars {
+        return Some(chars);
+    }
 
-    chars
-        + 1
-        + bs.windows(chars + 1)
-            .position(|w| {
-                let &[first, .., last] = w else {unreachable!()};
-                seen[(first - b'a') as usize] -= 1;
-                if seen[(first - b'a') as usize] == 0 {
+    Some(
+        chars
+            + 1
+            + bs.windows(chars + 1).position(|w| {
+                let &[first, .., last] = w else { unreachable!() };
+                seen[first as usize] -= 1;
+                if seen[first as usize] == 0 {
                     unique -= 1;
                 }
-                if seen[(last - b'a') as usize] == 0 {
+                if seen[last as usize] == 0 {
                     unique += 1;
                 }
-                seen[(last - b'a') as usize] += 1;
+                seen[last as usize] += 1;
                 unique == chars
-            })
-            .unwrap()
+            })?,
+    )
+}
+
+fn first_marker_bitmask(bs: &[u8], chars: usize) -> Option<usize> {
+    let mut start = 0;
+    while start + chars <= bs.len() {
+        // Going backwards, the first byte we've already seen is the last duplicate in the window: no window
+        // that still contains both copies can be a marker, so the next candidate starts right after it.
+        let mut seen = [0u64; 4];
+        let duplicate = bs[start..start + chars].iter().rposition(|&b| {
+            let bit = 1 << (b & 63);
+            let word = &mut seen[(b >> 6) as usize];
+            *word ^= bit;
+            *word & bit == 0
+        });
+        match duplicate {
+            Some(offset) => start += offset + 1,
+            None => return Some(start + chars),
+        }
+    }
+    None
+}
+
+/// A spot in the stream where the last `size` bytes were all different.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub size: usize,
+    /// How many bytes had been read when the marker was complete.
+    pub end: usize,
+}
+
+/// How many times each byte shows up in a window of the stream.
+#[derive(Debug, Clone)]
+struct Window {
+    size: usize,
+    seen: [u32; 256],
+    unique: usize,
+}
+
+/// Looks for markers of several sizes at once in a stream of bytes, fed to it in chunks.
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    windows: Vec<Window>,
+    /// The last few bytes, as a ring buffer big enough for the widest window.
+    history: Vec<u8>,
+    pos: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(sizes: &[usize]) -> Self {
+        assert!(sizes.iter().all(|&size| size != 0), "markers can't be empty");
+        Self {
+            windows: sizes
+                .iter()
+                .map(|&size| Window {
+                    size,
+                    seen: [0; 256],
+                    unique: 0,
+                })
+                .collect(),
+            history: vec![0; sizes.iter().copied().max().unwrap_or(1)],
+            pos: 0,
+        }
+    }
+
+    /// How many bytes have been fed to the detector so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn push(&mut self, byte: u8, mut on_marker: impl FnMut(Marker)) {
+        let cap = self.history.len();
+        for window in &mut self.windows {
+            if self.pos >= window.size {
+                let old = self.history[(self.pos - window.size) % cap] as usize;
+                window.seen[old] -= 1;
+                if window.seen[old] == 0 {
+                    window.unique -= 1;
+                }
+            }
+            if window.seen[byte as usize] == 0 {
+                window.unique += 1;
+            }
+            window.seen[byte as usize] += 1;
+
+            if window.unique == window.size {
+                on_marker(Marker {
+                    size: window.size,
+                    end: self.pos + 1,
+                });
+            }
+        }
+        self.history[self.pos % cap] = byte;
+        self.pos += 1;
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut on_marker: impl FnMut(Marker)) {
+        bytes.iter().for_each(|&byte| self.push(byte, &mut on_marker));
+    }
+
+    /// Feed the detector everything `reader` has to give, a chunk at a time.
+    pub fn scan(&mut self, mut reader: impl Read, mut on_marker: impl FnMut(Marker)) -> io::Result<()> {
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.feed(&buf[..n], &mut on_marker),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    (first_marker(4), first_marker(14))
+    let bs = include_str!("input.txt").trim().as_bytes();
+    (
+        first_marker(bs, 4, Algorithm::Counting).unwrap(),
+        first_marker(bs, 14, Algorithm::Counting).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[u8] = b"mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
+    #[test]
+    fn test_first_marker() {
+        for algorithm in [Algorithm::Counting, Algorithm::Bitmask] {
+            assert_eq!(first_marker(SAMPLE, 4, algorithm), Some(7));
+            assert_eq!(first_marker(SAMPLE, 14, algorithm), Some(19));
+            assert_eq!(first_marker(b"abcd", 4, algorithm), Some(4));
+            assert_eq!(first_marker(b"aaaa", 2, algorithm), None);
+            assert_eq!(first_marker(b"\x00\xff\x00\x80", 3, algorithm), Some(4));
+        }
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        let mut rng = fastrand::Rng::with_seed(0x2022_0006);
+        for alphabet in [2, 5, 14, 26, 64, 256] {
+            let signal = (0..4096).map(|_| rng.u32(..alphabet) as u8).collect::<Vec<_>>();
+            for chars in 1..=16 {
+                assert_eq!(
+                    first_marker(&signal, chars, Algorithm::Counting),
+                    first_marker(&signal, chars, Algorithm::Bitmask),
+                    "alphabet of {alphabet}, {chars} chars"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_detector() {
+        let mut expected = Vec::new();
+        for end in 1..=SAMPLE.len() {
+            for size in [4, 14] {
+                let Some(window) = end.checked_sub(size).map(|start| &SAMPLE[start..end]) else {
+                    continue;
+                };
+                if window.iter().all(|b| window.iter().filter(|&c| c == b).count() == 1) {
+                    expected.push(Marker { size, end });
+                }
+            }
+        }
+
+        for chunk in 1..=5 {
+            let mut found = Vec::new();
+            let mut detector = MarkerDetector::new(&[4, 14]);
+            SAMPLE
+                .chunks(chunk)
+                .for_each(|bytes| detector.feed(bytes, |marker| found.push(marker)));
+            assert_eq!(found, expected);
+        }
+
+        let mut found = Vec::new();
+        MarkerDetector::new(&[4, 14])
+            .scan(SAMPLE, |marker| found.push(marker))
+            .unwrap();
+        assert_eq!(found, expected);
+        assert_eq!(found.iter().find(|marker| marker.size == 14).unwrap().end, 19);
+    }
 }
@@ -1,6 +1,13 @@
 use std::fmt::Display;
 
-use peeking_take_while::PeekableExt;
+mod plan;
+mod query;
+mod report;
+mod tree;
+pub use plan::{PlanError, SpacePlanner};
+pub use query::{glob_match, ParseQueryError, Query};
+pub use report::{human_size, render, Entry, Format};
+pub use tree::{FileTree, Mode, NodeId, TranscriptError};
 
 const MAX_TO_SUM: u64 = 100_000;
 const DISK_SPACE: u64 = 70_000_000;
@@ -8,63 +15,279 @@ const NEEDED_SPACE: u64 = 30_000_000;
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    // ASSUMPTION: Directories are listed in DFS order, with no directory being ls-ed more than
-    // once.
-    let mut input = include_str!("input.txt").trim().lines().peekable();
-
-    let mut weights: Vec<u64> = Vec::with_capacity(256);
-    let mut stack: Vec<usize> = Vec::with_capacity(16);
-    let mut next_id = 0;
-
-    while let Some(line) = input.next() {
-        if let Some(dir) = line.strip_prefix("$ cd ") {
-            // We're changing directory
-            if dir == ".." {
-                // If we're going up, add the final weight of this directory to our parent.
-                let prev = stack.pop().unwrap();
-                let final_weight = weights[prev];
-                weights[*stack.last().unwrap()] += final_weight;
-            } else {
-                // If we're going down, assign this directory an ID.
-                stack.push(next_id);
-                next_id += 1;
-                weights.push(0);
-            }
-        } else {
-            // line must be "$ ls"
-            // For each file in the list, add its size to the current directory.
-            // Ignoring directories, as those are handled when going up.
-            let &cwd = stack.last().unwrap();
-            input
-                .by_ref()
-                .peeking_take_while(|line| !line.starts_with('$'))
-                .map(|line| line.split_once(' ').unwrap())
-                .filter(|&(ty, _)| ty != "dir")
-                .for_each(|(size, _)| weights[cwd] += size.parse::<u64>().unwrap());
-        }
+    let tree = include_str!("input.txt").parse::<FileTree>().unwrap();
+
+    // Sum up all the small-enough directories.
+    let p1: u64 = tree
+        .dirs()
+        .map(|id| tree.size(id))
+        .filter(|&size| size <= MAX_TO_SUM)
+        .sum();
+
+    // Free up enough space, deleting as little as we can.
+    let plan = SpacePlanner::new(DISK_SPACE, NEEDED_SPACE).plan(&tree).unwrap();
+    let p2: u64 = plan.iter().map(|&id| tree.size(id)).sum();
+
+    (p1, p2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+    #[test]
+    fn test_tree() {
+        let tree = SAMPLE.parse::<FileTree>().unwrap();
+        assert_eq!(tree.size(tree.root()), 48381165);
+        assert_eq!(tree.size(tree.lookup("/a").unwrap()), 94853);
+        assert_eq!(tree.size(tree.lookup("/a/e").unwrap()), 584);
+        assert_eq!(tree.size(tree.lookup("/d/k").unwrap()), 7214296);
+        assert_eq!(tree.lookup("/a/x"), None);
+
+        let names = tree.children(tree.root()).map(|id| tree.name(id)).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b.txt", "c.dat", "d"]);
+        assert_eq!(tree.dirs().count(), 4);
+        assert_eq!(tree.files().count(), 10);
+
+        let target = tree.smallest_dir_over(8381165).unwrap();
+        assert_eq!((tree.path(target).as_str(), tree.size(target)), ("/d", 24933642));
     }
 
-    // After our traversal is over, add up what we didn't `cd` out of.
-    loop {
-        let prev = stack.pop().unwrap();
-        if prev == 0 {
-            break;
+    #[test]
+    fn test_any_order() {
+        // Visit `d` before `a`, jump back to the root, and list `a` twice.
+        let shuffled = "\
+$ cd /
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+";
+        assert_eq!(FileTree::validate(shuffled), []);
+        let tree = FileTree::parse(shuffled, Mode::Strict).unwrap();
+        let expected = SAMPLE.parse::<FileTree>().unwrap();
+        for dir in ["/", "/a", "/a/e", "/d"] {
+            assert_eq!(
+                tree.size(tree.lookup(dir).unwrap()),
+                expected.size(expected.lookup(dir).unwrap())
+            );
         }
-        let final_weight = weights[prev];
-        weights[*stack.last().unwrap()] += final_weight;
+        assert_eq!(
+            "$ cd /\n$ ls\nhello world".parse::<FileTree>().unwrap_err(),
+            TranscriptError::Syntax { line: 3 }
+        );
     }
 
-    // Sum up all the small-enough directories.
-    let p1: u64 = weights.iter().filter(|&&v| v <= MAX_TO_SUM).sum();
-
-    // Get the smallest directory big enough to be worth deleting.
-    let delete_target = NEEDED_SPACE - (DISK_SPACE - weights[0]);
-    let p2 = weights
-        .iter()
-        .filter(|&&size| size >= delete_target)
-        .min()
-        .copied()
-        .unwrap();
+    #[test]
+    fn test_validate() {
+        let transcript = "\
+$ cd /
+$ ls
+dir a
+100 b
+$ cd ..
+$ cd a
+$ ls
+10 c
+$ cd ..
+$ ls
+dir a
+200 b
+$ cd x
+$ ls
+5 y
+$ cd y
+";
+        let expected = [
+            TranscriptError::AboveRoot { line: 5 },
+            TranscriptError::ConflictingListing {
+                line: 10,
+                first_line: 2,
+                dir: "/".to_owned(),
+            },
+            TranscriptError::UnlistedDir {
+                line: 13,
+                dir: "/x".to_owned(),
+            },
+            TranscriptError::NotADir {
+                line: 16,
+                path: "/x/y".to_owned(),
+            },
+        ];
+        assert_eq!(FileTree::validate(transcript), expected);
+        assert_eq!(FileTree::validate(SAMPLE), []);
 
-    (p1, p2)
+        assert_eq!(FileTree::parse(transcript, Mode::Strict).unwrap_err(), expected[0]);
+        assert_eq!(transcript.parse::<FileTree>().unwrap_err(), expected[0]);
+        let tree = FileTree::parse(transcript, Mode::Lenient).unwrap();
+        assert_eq!(tree.size(tree.root()), 215);
+        assert_eq!(tree.size(tree.lookup("/b").unwrap()), 200);
+    }
+
+    #[test]
+    fn test_reports() {
+        let tree = SAMPLE.parse::<FileTree>().unwrap();
+        assert_eq!(
+            render(&tree.du(), Format::Text { human: true }),
+            "584\t/a/e\n93K\t/a\n24M\t/d\n47M\t/\n"
+        );
+        assert_eq!(
+            render(&tree.du()[..1], Format::Json),
+            r#"[{"path":"/a/e","size":584,"type":"dir"}]"#
+        );
+        let largest = tree
+            .largest_files(3)
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect::<Vec<_>>();
+        assert_eq!(largest, ["/b.txt", "/c.dat", "/d/d.log"]);
+
+        let expected = "\
+/ (48381165)
+├── a (94853)
+│   ├── e (584)
+│   │   └── i (584)
+│   ├── f (29116)
+│   ├── g (2557)
+│   └── h.lst (62596)
+├── b.txt (14848514)
+├── c.dat (8504156)
+└── d (24933642)
+    ├── d.ext (5626152)
+    ├── d.log (8033020)
+    ├── j (4060174)
+    └── k (7214296)
+";
+        assert_eq!(tree.render_tree(Format::Text { human: false }), expected);
+        assert!(tree
+            .render_tree(Format::Json)
+            .starts_with(r#"{"name":"/","size":48381165,"type":"dir","children":[{"name":"a","#));
+        assert_eq!(human_size(2557), "2.5K");
+        assert_eq!(human_size(10239), "10K");
+        assert_eq!(human_size(1048575), "1.0M");
+    }
+
+    #[test]
+    fn test_query() {
+        let tree = SAMPLE.parse::<FileTree>().unwrap();
+        let paths = |query: &str| {
+            let query = query.parse::<Query>().unwrap();
+            tree.query(&query)
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths("dirs size<=100000"), ["/a", "/a/e"]);
+        assert_eq!(
+            paths("files name=*.*"),
+            ["/a/h.lst", "/b.txt", "/c.dat", "/d/d.ext", "/d/d.log"]
+        );
+        assert_eq!(paths("files path=/d/* size>=6M"), ["/d/d.log", "/d/k"]);
+        assert_eq!(paths("size=584"), ["/a/e", "/a/e/i"]);
+        assert_eq!(
+            "dirs size~3".parse::<Query>().unwrap_err(),
+            ParseQueryError {
+                term: "size~3".to_owned()
+            }
+        );
+        assert!(glob_match("a*b*c", "aXbYbc"));
+        assert!(!glob_match("a*c", "ab"));
+    }
+
+    #[test]
+    fn test_planner() {
+        let tree = SAMPLE.parse::<FileTree>().unwrap();
+        let paths = |planner: SpacePlanner| {
+            planner
+                .plan(&tree)
+                .map(|plan| plan.into_iter().map(|id| tree.path(id)).collect::<Vec<_>>())
+        };
+        assert_eq!(paths(SpacePlanner::new(DISK_SPACE, NEEDED_SPACE)).unwrap(), ["/d"]);
+        assert_eq!(
+            paths(SpacePlanner::new(DISK_SPACE, 20_000_000)).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            paths(SpacePlanner::new(DISK_SPACE, NEEDED_SPACE).protect("/d/k")),
+            Err(PlanError::NotEnoughSpace)
+        );
+        assert_eq!(
+            paths(SpacePlanner::new(DISK_SPACE, 20_000_000).protect("/d/kk")),
+            Err(PlanError::UnknownPath("/d/kk".to_owned()))
+        );
+
+        // With the root off limits, no one directory is big enough.
+        let tree = "\
+$ cd /
+$ ls
+dir x
+dir y
+1 keep
+$ cd x
+$ ls
+500 p
+dir z
+$ cd z
+$ ls
+300 q
+$ cd /
+$ cd y
+$ ls
+400 r
+"
+        .parse::<FileTree>()
+        .unwrap();
+        let plan = SpacePlanner::new(1300, 1099).protect("/keep").plan(&tree).unwrap();
+        assert_eq!(plan.iter().map(|&id| tree.path(id)).collect::<Vec<_>>(), ["/x", "/y"]);
+        let plan = SpacePlanner::new(1300, 700).protect("/keep").plan(&tree).unwrap();
+        assert_eq!(plan.iter().map(|&id| tree.path(id)).collect::<Vec<_>>(), ["/x"]);
+    }
 }
@@ -0,0 +1,104 @@
+use std::{collections::HashSet, error::Error, fmt::Display};
+
+use crate::{FileTree, NodeId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// A protected path that isn't in the tree, which is most likely a typo.
+    UnknownPath(String),
+    /// Deleting everything that isn't protected still doesn't free up enough space.
+    NotEnoughSpace,
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanError::UnknownPath(path) => write!(f, "can't protect {path}, which doesn't exist"),
+            PlanError::NotEnoughSpace => write!(f, "can't free up enough space without deleting something protected"),
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+/// Works out which directories to delete to make room on a disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpacePlanner {
+    disk_space: u64,
+    needed_space: u64,
+    protected: Vec<String>,
+}
+
+impl SpacePlanner {
+    /// Plan for a disk holding `disk_space` in total, which needs `needed_space` of it free.
+    pub fn new(disk_space: u64, needed_space: u64) -> Self {
+        Self {
+            disk_space,
+            needed_space,
+            protected: Vec::new(),
+        }
+    }
+
+    /// Never delete `path`, nor any directory it's in.
+    pub fn protect(mut self, path: &str) -> Self {
+        self.protected.push(path.to_owned());
+        self
+    }
+
+    /// How much more space needs freeing up.
+    pub fn shortfall(&self, tree: &FileTree) -> u64 {
+        let free = self.disk_space.saturating_sub(tree.size(tree.root()));
+        self.needed_space.saturating_sub(free)
+    }
+
+    /// The fewest directories that free up enough space between them, none of them inside another. That's fewest
+    /// by count, not the least space deleted: of the ways to get away with that few, only the last directory
+    /// picked is the smallest that does the job.
+    ///
+    /// There's nothing to delete if there's already enough space.
+    pub fn plan(&self, tree: &FileTree) -> Result<Vec<NodeId>, PlanError> {
+        let mut blocked = HashSet::new();
+        for path in &self.protected {
+            let id = tree.lookup(path).ok_or_else(|| PlanError::UnknownPath(path.clone()))?;
+            blocked.extend([id].into_iter().chain(tree.ancestors(id)));
+        }
+
+        let mut shortfall = self.shortfall(tree);
+        if shortfall == 0 {
+            return Ok(Vec::new());
+        }
+        let deletable = |id: NodeId| tree.is_dir(id) && !blocked.contains(&id);
+
+        // Deleting a directory that isn't in any other deletable one frees the most, so use the biggest of those
+        // until one more will do.
+        let mut outermost = tree
+            .dirs()
+            .filter(|&id| deletable(id) && tree.parent(id).is_none_or(|parent| !deletable(parent)))
+            .collect::<Vec<_>>();
+        outermost.sort_by_key(|&id| std::cmp::Reverse(tree.size(id)));
+        if outermost.iter().map(|&id| tree.size(id)).sum::<u64>() < shortfall {
+            return Err(PlanError::NotEnoughSpace);
+        }
+
+        let mut plan = Vec::new();
+        for &id in &outermost {
+            if tree.size(id) >= shortfall {
+                break;
+            }
+            plan.push(id);
+            shortfall -= tree.size(id);
+        }
+        let last = tree
+            .dirs()
+            .filter(|&id| deletable(id) && tree.size(id) >= shortfall)
+            .filter(|&id| {
+                !plan
+                    .iter()
+                    .any(|&picked| id == picked || tree.ancestors(id).any(|dir| dir == picked))
+            })
+            .min_by_key(|&id| tree.size(id))
+            .ok_or(PlanError::NotEnoughSpace)?;
+        plan.push(last);
+        Ok(plan)
+    }
+}
@@ -0,0 +1,161 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use crate::{FileTree, NodeId};
+
+/// Picks out files and directories, e.g. `dirs size<=100000` or `files name=*.txt size>1M`.
+///
+/// A query is a list of terms separated by spaces, all of which have to match:
+/// - `dirs` or `files`
+/// - `size` compared with `<`, `<=`, `=`, `>=` or `>` to a number of bytes, optionally suffixed with `K`, `M`
+///   or `G` (powers of 1024)
+/// - `name=GLOB` or `path=GLOB`, where `*` matches any run of characters and `?` any one character
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    kind: Option<Kind>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    name: Option<String>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Dir,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseQueryError {
+    pub term: String,
+}
+
+impl Display for ParseQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "don't know what to make of `{}` in a query", self.term)
+    }
+}
+
+impl Error for ParseQueryError {}
+
+/// A size like `1500`, `4K` or `2M`.
+fn parse_size(s: &str) -> Option<u64> {
+    let (digits, shift) = match s.as_bytes().last()? {
+        b'K' | b'k' => (&s[..s.len() - 1], 10),
+        b'M' | b'm' => (&s[..s.len() - 1], 20),
+        b'G' | b'g' => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+impl FromStr for Query {
+    type Err = ParseQueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace().try_fold(Self::new(), |query, term| {
+            let bad = || ParseQueryError { term: term.to_owned() };
+            if let Some(glob) = term.strip_prefix("name=") {
+                return Ok(query.name(glob));
+            }
+            if let Some(glob) = term.strip_prefix("path=") {
+                return Ok(query.path(glob));
+            }
+            Ok(match term {
+                "dirs" => query.dirs(),
+                "files" => query.files(),
+                _ => {
+                    let rest = term.strip_prefix("size").ok_or_else(bad)?;
+                    let op_len = rest.find(|ch: char| ch.is_ascii_digit()).ok_or_else(bad)?;
+                    let size = parse_size(&rest[op_len..]).ok_or_else(bad)?;
+                    match &rest[..op_len] {
+                        "<" => query.max_size(size.checked_sub(1).ok_or_else(bad)?),
+                        "<=" => query.max_size(size),
+                        "=" => query.min_size(size).max_size(size),
+                        ">=" => query.min_size(size),
+                        ">" => query.min_size(size.checked_add(1).ok_or_else(bad)?),
+                        _ => return Err(bad()),
+                    }
+                }
+            })
+        })
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters and `?` any one character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it's swallowed so far.
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and try again.
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+impl Query {
+    /// A query matching everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dirs(mut self) -> Self {
+        self.kind = Some(Kind::Dir);
+        self
+    }
+
+    pub fn files(mut self) -> Self {
+        self.kind = Some(Kind::File);
+        self
+    }
+
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = Some(size);
+        self
+    }
+
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Only match things whose own name matches a glob.
+    pub fn name(mut self, glob: &str) -> Self {
+        self.name = Some(glob.to_owned());
+        self
+    }
+
+    /// Only match things whose full path, like `/a/e/i`, matches a glob.
+    pub fn path(mut self, glob: &str) -> Self {
+        self.path = Some(glob.to_owned());
+        self
+    }
+
+    pub fn matches(&self, tree: &FileTree, id: NodeId) -> bool {
+        let size = tree.size(id);
+        self.kind.is_none_or(|kind| (kind == Kind::Dir) == tree.is_dir(id))
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+            && self.name.as_ref().is_none_or(|glob| glob_match(glob, tree.name(id)))
+            && self.path.as_ref().is_none_or(|glob| glob_match(glob, &tree.path(id)))
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::Write;
+
+use crate::{FileTree, NodeId, Query};
+
+/// A file or directory in a report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+impl Entry {
+    pub fn new(tree: &FileTree, id: NodeId) -> Self {
+        Self {
+            path: tree.path(id),
+            size: tree.size(id),
+            is_dir: tree.is_dir(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One line per entry, with sizes in bytes or, if `human`, rounded up to `K`, `M`, `G` and so on.
+    Text {
+        human: bool,
+    },
+    Json,
+}
+
+/// A size the way `du -h` shows it: `584`, `2.5K`, `24M`.
+pub fn human_size(size: u64) -> String {
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64;
+    let mut units = "KMGTPE".chars();
+    let mut unit = ' ';
+    while value >= 1024.0 {
+        value /= 1024.0;
+        unit = units.next().unwrap();
+    }
+    let mut rounded = if value < 10.0 {
+        (value * 10.0).ceil() / 10.0
+    } else {
+        value.ceil()
+    };
+    // Rounding up can take us to the next unit, as in 1048575 bytes being 1.0M rather than 1024K.
+    if rounded >= 1024.0 {
+        rounded = 1.0;
+        unit = units.next().unwrap();
+    }
+    if rounded < 10.0 {
+        format!("{rounded:.1}{unit}")
+    } else {
+        format!("{rounded}{unit}")
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn kind(is_dir: bool) -> &'static str {
+    if is_dir {
+        "dir"
+    } else {
+        "file"
+    }
+}
+
+/// Lay out entries like `du` does, with a size and a path on each line, or as a JSON array.
+pub fn render(entries: &[Entry], format: Format) -> String {
+    match format {
+        Format::Text { human } => entries
+            .iter()
+            .map(|entry| {
+                let size = if human {
+                    human_size(entry.size)
+                } else {
+                    entry.size.to_string()
+                };
+                format!("{size}\t{}\n", entry.path)
+            })
+            .collect(),
+        Format::Json => {
+            let entries = entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        r#"{{"path":{},"size":{},"type":"{}"}}"#,
+                        json_string(&entry.path),
+                        entry.size,
+                        kind(entry.is_dir)
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", entries.join(","))
+        }
+    }
+}
+
+impl FileTree {
+    /// Everything under `id`, each directory's contents sorted by name and coming right after it.
+    fn walk(&self, id: NodeId, out: &mut Vec<NodeId>) {
+        out.push(id);
+        self.children(id).for_each(|child| self.walk(child, out));
+    }
+
+    /// Everything in the tree matching a query, in the order `tree` lists them: each directory's contents sorted
+    /// by name and coming right after it, so `/a/x` comes before `/a.txt`.
+    pub fn query(&self, query: &Query) -> Vec<Entry> {
+        let mut ids = Vec::new();
+        self.walk(self.root(), &mut ids);
+        ids.into_iter()
+            .filter(|&id| query.matches(self, id))
+            .map(|id| Entry::new(self, id))
+            .collect()
+    }
+
+    /// Every directory, each one coming after everything in it, the way `du` lists them.
+    pub fn du(&self) -> Vec<Entry> {
+        fn walk_dirs(tree: &FileTree, id: NodeId, out: &mut Vec<Entry>) {
+            tree.children(id)
+                .filter(|&child| tree.is_dir(child))
+                .for_each(|child| walk_dirs(tree, child, out));
+            out.push(Entry::new(tree, id));
+        }
+
+        let mut dirs = Vec::new();
+        walk_dirs(self, self.root(), &mut dirs);
+        dirs
+    }
+
+    /// The `n` biggest files, biggest first.
+    pub fn largest_files(&self, n: usize) -> Vec<Entry> {
+        let mut files = self.query(&Query::new().files());
+        files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        files.truncate(n);
+        files
+    }
+
+    /// Draw the tree like `tree` does, with the size of everything in parentheses, or as nested JSON objects.
+    pub fn render_tree(&self, format: Format) -> String {
+        let mut out = String::new();
+        match format {
+            Format::Text { human } => self.tree_text(self.root(), human, "", &mut out),
+            Format::Json => self.tree_json(self.root(), &mut out),
+        }
+        out
+    }
+
+    fn tree_text(&self, id: NodeId, human: bool, prefix: &str, out: &mut String) {
+        let size = if human {
+            human_size(self.size(id))
+        } else {
+            self.size(id).to_string()
+        };
+        let name = if id == self.root() { "/" } else { self.name(id) };
+        writeln!(out, "{name} ({size})").unwrap();
+
+        let children = self.children(id).collect::<Vec<_>>();
+        for (idx, &child) in children.iter().enumerate() {
+            let last = idx + 1 == children.len();
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            self.tree_text(child, human, &prefix, out);
+        }
+    }
+
+    fn tree_json(&self, id: NodeId, out: &mut String) {
+        let name = if id == self.root() { "/" } else { self.name(id) };
+        write!(
+            out,
+            r#"{{"name":{},"size":{},"type":"{}""#,
+            json_string(name),
+            self.size(id),
+            kind(self.is_dir(id))
+        )
+        .unwrap();
+        if self.is_dir(id) {
+            out.push_str(r#","children":["#);
+            for (idx, child) in self.children(id).enumerate() {
+                if idx != 0 {
+                    out.push(',');
+                }
+                self.tree_json(child, out);
+            }
+            out.push(']');
+        }
+        out.push('}');
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
+/// A file or directory in a [`FileTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    /// For directories, the total size of everything in them.
+    size: u64,
+    /// `None` for files. Sorted by name.
+    children: Option<Vec<NodeId>>,
+}
+
+/// The filesystem, as pieced together from a terminal transcript.
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    /// Every node comes after its parent, so walking this backwards visits children before parents.
+    nodes: Vec<Node>,
+}
+
+/// How to deal with a transcript that doesn't add up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Fail on the first inconsistency.
+    Strict,
+    /// Merge every listing of a directory, with the latest size of a file winning; create directories that are
+    /// `cd`-ed into without being listed; and stay put on `cd ..` at the root or `cd` into a file.
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptError {
+    /// A line that isn't a command we know or a directory listing entry.
+    Syntax { line: usize },
+    /// A directory listed again with different contents from the first time, on line `first_line`.
+    ConflictingListing {
+        line: usize,
+        first_line: usize,
+        dir: String,
+    },
+    /// A `cd` into a directory that no listing mentions.
+    UnlistedDir { line: usize, dir: String },
+    /// A `cd ..` at the root.
+    AboveRoot { line: usize },
+    /// A `cd` into something listed as a file.
+    NotADir { line: usize, path: String },
+}
+
+impl TranscriptError {
+    pub fn line(&self) -> usize {
+        match *self {
+            TranscriptError::Syntax { line }
+            | TranscriptError::ConflictingListing { line, .. }
+            | TranscriptError::UnlistedDir { line, .. }
+            | TranscriptError::AboveRoot { line }
+            | TranscriptError::NotADir { line, .. } => line,
+        }
+    }
+}
+
+impl Display for TranscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscriptError::Syntax { line } => write!(f, "line {line}: expected `$ cd`, `$ ls` or a listing entry"),
+            TranscriptError::ConflictingListing { line, first_line, dir } => {
+                write!(f, "line {line}: {dir} was listed differently on line {first_line}")
+            }
+            TranscriptError::UnlistedDir { line, dir } => write!(f, "line {line}: {dir} is never listed"),
+            TranscriptError::AboveRoot { line } => write!(f, "line {line}: can't go up from the root"),
+            TranscriptError::NotADir { line, path } => write!(f, "line {line}: {path} is a file"),
+        }
+    }
+}
+
+impl Error for TranscriptError {}
+
+/// What a listing said was in a directory, with `None` for directories.
+type Listing<'a> = BTreeMap<&'a str, Option<u64>>;
+
+/// Read back the entries of a listing, which have already been checked to be well-formed.
+fn read_listing<'a>(entries: &[&'a str]) -> Listing<'a> {
+    entries
+        .iter()
+        .map(|entry| match entry.strip_prefix("dir ") {
+            Some(name) => (name, None),
+            None => {
+                let (size, name) = entry.split_once(' ').unwrap();
+                (name, Some(size.parse().unwrap()))
+            }
+        })
+        .collect()
+}
+
+impl FromStr for FileTree {
+    type Err = TranscriptError;
+
+    /// Parse a transcript in [`Mode::Strict`]; use [`FileTree::parse`] to be lenient.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Mode::Strict)
+    }
+}
+
+impl FileTree {
+    pub fn parse(transcript: &str, mode: Mode) -> Result<Self, TranscriptError> {
+        let mut problems = Vec::new();
+        let tree = Self::build(transcript, mode == Mode::Strict, &mut problems)?;
+        match problems.into_iter().min_by_key(TranscriptError::line) {
+            Some(problem) if mode == Mode::Strict => Err(problem),
+            _ => Ok(tree),
+        }
+    }
+
+    /// Everything wrong with a transcript, in order.
+    pub fn validate(transcript: &str) -> Vec<TranscriptError> {
+        let mut problems = Vec::new();
+        if let Err(error) = Self::build(transcript, true, &mut problems) {
+            problems.push(error);
+        }
+        problems.sort_by_key(TranscriptError::line);
+        problems
+    }
+
+    /// Build the tree leniently, noting down any inconsistencies along the way if `check` is set. Only bad syntax
+    /// stops it.
+    fn build(transcript: &str, check: bool, problems: &mut Vec<TranscriptError>) -> Result<Self, TranscriptError> {
+        let mut tree = Self {
+            nodes: vec![Node {
+                name: String::new(),
+                parent: None,
+                size: 0,
+                children: Some(Vec::new()),
+            }],
+        };
+
+        let lines = transcript.lines().collect::<Vec<_>>();
+        // Where the first listing of each directory is, by the index of its `ls` line and the index just past its
+        // last entry. Most directories are only listed once, so listings are only read back to be compared when
+        // there's a second one.
+        let mut listings = HashMap::<NodeId, (usize, usize)>::new();
+        // The directory being listed, if any, and the index of its `ls` line.
+        let mut listing: Option<(NodeId, usize)> = None;
+        let mut finish_listing = |tree: &FileTree, problems: &mut Vec<_>, listing: Option<(NodeId, usize)>, end| {
+            let Some((dir, start)) = listing else {
+                return;
+            };
+            match listings.get(&dir) {
+                Some(&(first_start, first_end))
+                    if read_listing(&lines[first_start + 1..first_end]) != read_listing(&lines[start + 1..end]) =>
+                {
+                    problems.push(TranscriptError::ConflictingListing {
+                        line: start + 1,
+                        first_line: first_start + 1,
+                        dir: tree.path(dir),
+                    })
+                }
+                Some(_) => {}
+                None => {
+                    listings.insert(dir, (start, end));
+                }
+            }
+        };
+        // Directories a listing has mentioned, and the directories `cd`-ed into along with the line each time.
+        let mut listed = vec![tree.root()];
+        let mut entered = Vec::new();
+
+        let mut cwd = tree.root();
+        for (idx, &line) in lines.iter().enumerate() {
+            let line_no = idx + 1;
+            if line.starts_with('$') {
+                finish_listing(&tree, problems, listing.take(), idx);
+            }
+
+            if let Some(dir) = line.strip_prefix("$ cd ") {
+                cwd = match dir {
+                    "/" => tree.root(),
+                    ".." => tree.parent(cwd).unwrap_or_else(|| {
+                        problems.push(TranscriptError::AboveRoot { line: line_no });
+                        cwd
+                    }),
+                    name => match tree.insert(cwd, name, None) {
+                        id if !tree.is_dir(id) => {
+                            problems.push(TranscriptError::NotADir {
+                                line: line_no,
+                                path: tree.path(id),
+                            });
+                            cwd
+                        }
+                        id => {
+                            if check {
+                                entered.push((id, line_no));
+                            }
+                            id
+                        }
+                    },
+                };
+            } else if line == "$ ls" {
+                // Only worth keeping track of to compare with other listings of the same directory.
+                listing = check.then_some((cwd, idx));
+            } else if let Some(name) = line.strip_prefix("dir ") {
+                let id = tree.insert(cwd, name, None);
+                if check {
+                    listed.push(id);
+                }
+            } else {
+                let (size, name) = line
+                    .split_once(' ')
+                    .and_then(|(size, name)| Some((size.parse().ok()?, name)))
+                    .ok_or(TranscriptError::Syntax { line: line_no })?;
+                tree.insert(cwd, name, Some(size));
+            }
+        }
+        finish_listing(&tree, problems, listing, lines.len());
+
+        if check {
+            // Only complain about each unlisted directory once, the first time it's entered.
+            let mut seen = vec![false; tree.nodes.len()];
+            listed.into_iter().for_each(|NodeId(id)| seen[id] = true);
+            for (id, line) in entered {
+                if !std::mem::replace(&mut seen[id.0], true) {
+                    problems.push(TranscriptError::UnlistedDir {
+                        line,
+                        dir: tree.path(id),
+                    });
+                }
+            }
+        }
+
+        for idx in (1..tree.nodes.len()).rev() {
+            let NodeId(parent) = tree.nodes[idx].parent.unwrap();
+            tree.nodes[parent].size += tree.nodes[idx].size;
+        }
+        Ok(tree)
+    }
+
+    /// Add a file (if `size` is given) or a directory, returning the existing node if there's one by that name.
+    /// An existing file takes on the new size.
+    fn insert(&mut self, dir: NodeId, name: &str, size: Option<u64>) -> NodeId {
+        let pos = match self.find_child(dir, name) {
+            Ok(pos) => {
+                let id = self.nodes[dir.0].children.as_ref().unwrap()[pos];
+                if let (Some(size), false) = (size, self.is_dir(id)) {
+                    self.nodes[id.0].size = size;
+                }
+                return id;
+            }
+            Err(pos) => pos,
+        };
+
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(dir),
+            size: size.unwrap_or(0),
+            children: size.is_none().then(Vec::new),
+        });
+        self.nodes[dir.0].children.as_mut().unwrap().insert(pos, id);
+        id
+    }
+
+    /// Where the child called `name` is in a directory's children, or where it would go.
+    fn find_child(&self, dir: NodeId, name: &str) -> Result<usize, usize> {
+        self.nodes[dir.0].children.as_ref().map_or(Err(0), |children| {
+            children.binary_search_by(|&id| self.nodes[id.0].name.as_str().cmp(name))
+        })
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.nodes[id.0].name
+    }
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        self.nodes[id.0].children.is_some()
+    }
+
+    /// The size of a file, or the total size of everything in a directory.
+    pub fn size(&self, id: NodeId) -> u64 {
+        self.nodes[id.0].size
+    }
+
+    /// The directories `id` is in, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |&id| self.parent(id))
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        let pos = self.find_child(dir, name).ok()?;
+        Some(self.nodes[dir.0].children.as_ref()?[pos])
+    }
+
+    /// The contents of a directory, sorted by name.
+    pub fn children(&self, dir: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes[dir.0]
+            .children
+            .iter()
+            .flat_map(|children| children.iter().copied())
+    }
+
+    /// Look up an absolute path like `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(self.root(), |dir, name| self.child(dir, name))
+    }
+
+    pub fn path(&self, id: NodeId) -> String {
+        let mut parts = Vec::new();
+        let mut node = Some(id);
+        while let Some(id) = node.filter(|&id| id != self.root()) {
+            parts.push(self.name(id));
+            node = self.parent(id);
+        }
+        if parts.is_empty() {
+            return "/".to_owned();
+        }
+        parts.iter().rev().fold(String::new(), |path, part| path + "/" + part)
+    }
+
+    /// Every file and directory, root included, parents before their children.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes().filter(|&id| self.is_dir(id))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes().filter(|&id| !self.is_dir(id))
+    }
+
+    /// The smallest directory holding at least `size` worth of files.
+    pub fn smallest_dir_over(&self, size: u64) -> Option<NodeId> {
+        self.dirs()
+            .filter(|&id| self.size(id) >= size)
+            .min_by_key(|&id| self.size(id))
+    }
+}
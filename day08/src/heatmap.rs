@@ -0,0 +1,147 @@
+use std::fmt::Write;
+
+use crate::{sweep, Grid};
+
+/// Which way a tree is looking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+}
+
+/// What one tree can see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeView {
+    pub height: i8,
+    distances: [usize; 4],
+    visible_from: [bool; 4],
+}
+
+impl TreeView {
+    /// How many trees this one can see looking in a direction.
+    pub fn distance(&self, direction: Direction) -> usize {
+        self.distances[direction as usize]
+    }
+
+    /// Whether this tree can be seen from outside the forest, looking back at it from a direction.
+    pub fn visible_from(&self, direction: Direction) -> bool {
+        self.visible_from[direction as usize]
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible_from.contains(&true)
+    }
+
+    pub fn scenic_score(&self) -> usize {
+        self.distances.iter().product()
+    }
+}
+
+/// What to colour a heatmap by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heatmap {
+    /// How many directions a tree can be seen from.
+    Visibility,
+    /// The scenic score, on a log scale since a few trees have much better views than the rest.
+    ScenicScore,
+}
+
+/// What every tree in a forest can see, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Survey {
+    width: usize,
+    height: usize,
+    trees: Vec<TreeView>,
+}
+
+impl Survey {
+    pub fn new(grid: &Grid) -> Self {
+        let mut trees = grid
+            .heights
+            .iter()
+            .map(|&height| TreeView {
+                height,
+                distances: [0; 4],
+                visible_from: [false; 4],
+            })
+            .collect::<Vec<_>>();
+
+        sweep(grid, |idx, direction, distance, visible| {
+            trees[idx].distances[direction as usize] = distance;
+            trees[idx].visible_from[direction as usize] = visible;
+        });
+
+        Self {
+            width: grid.width,
+            height: grid.height,
+            trees,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The tree at `(x, y)`, counting from the top left.
+    pub fn get(&self, x: usize, y: usize) -> &TreeView {
+        &self.trees[y * self.width + x]
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.trees.iter().filter(|tree| tree.is_visible()).count()
+    }
+
+    pub fn best_score(&self) -> usize {
+        self.trees.iter().map(TreeView::scenic_score).max().unwrap_or(0)
+    }
+
+    /// How bright each tree should be, from 0 to 255.
+    fn levels(&self, heatmap: Heatmap) -> Vec<u8> {
+        match heatmap {
+            Heatmap::Visibility => self
+                .trees
+                .iter()
+                .map(|tree| (tree.visible_from.iter().filter(|&&seen| seen).count() * 255 / 4) as u8)
+                .collect(),
+            Heatmap::ScenicScore => {
+                let scale = (self.best_score() as f64).ln_1p().max(f64::MIN_POSITIVE);
+                self.trees
+                    .iter()
+                    .map(|tree| ((tree.scenic_score() as f64).ln_1p() / scale * 255.0).round() as u8)
+                    .collect()
+            }
+        }
+    }
+
+    /// The forest as a grid of tree heights on a grey background that gets lighter the hotter the tree is, for a
+    /// terminal that understands 256-colour ANSI escapes.
+    pub fn ansi(&self, heatmap: Heatmap) -> String {
+        let levels = self.levels(heatmap);
+        let mut out = String::new();
+        for (row, trees) in levels.chunks(self.width).zip(self.trees.chunks(self.width)) {
+            for (&level, tree) in row.iter().zip(trees) {
+                // The greyscale ramp goes from 232 (nearly black) to 255 (nearly white).
+                let background = 232 + level as usize * 23 / 255;
+                let foreground = if background < 244 { 255 } else { 232 };
+                write!(out, "\x1b[38;5;{foreground};48;5;{background}m{}", tree.height).unwrap();
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// The heatmap as a binary PGM image, one pixel per tree.
+    pub fn pgm(&self, heatmap: Heatmap) -> Vec<u8> {
+        aoc_pgm::encode(self.width, self.height, self.levels(heatmap))
+    }
+}
@@ -1,49 +1,239 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display, str::FromStr};
 
 use bitvec::prelude::*;
 
-const GRID_SIDE: usize = 99;
+mod heatmap;
+pub use heatmap::{Direction, Heatmap, Survey, TreeView};
 
-fn solve_part1(grid: &[i8]) -> usize {
-    let mut visibility = bitarr![0; GRID_SIDE * GRID_SIDE];
+/// A forest of trees with heights from 0 to 9, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    heights: Vec<i8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseGridError {
+    Empty,
+    /// A row that isn't as long as the first one.
+    Ragged {
+        line: usize,
+    },
+    /// Something other than a digit.
+    BadHeight {
+        line: usize,
+        column: usize,
+    },
+}
+
+impl Display for ParseGridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseGridError::Empty => write!(f, "the grid is empty"),
+            ParseGridError::Ragged { line } => write!(f, "line {line}: row isn't as long as the first one"),
+            ParseGridError::BadHeight { line, column } => {
+                write!(f, "line {line}, column {column}: expected a digit")
+            }
+        }
+    }
+}
+
+impl Error for ParseGridError {}
+
+impl FromStr for Grid {
+    type Err = ParseGridError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut width = None;
+        let mut heights = Vec::new();
+        for (idx, line) in s.lines().map(str::trim_end).enumerate() {
+            if *width.get_or_insert(line.len()) != line.len() {
+                return Err(ParseGridError::Ragged { line: idx + 1 });
+            }
+            for (column, ch) in line.bytes().enumerate() {
+                if !ch.is_ascii_digit() {
+                    return Err(ParseGridError::BadHeight {
+                        line: idx + 1,
+                        column: column + 1,
+                    });
+                }
+                heights.push((ch - b'0') as i8);
+            }
+        }
+        match width {
+            Some(width) if width != 0 => Ok(Self {
+                width,
+                height: heights.len() / width,
+                heights,
+            }),
+            _ => Err(ParseGridError::Empty),
+        }
+    }
+}
+
+impl Grid {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The height of the tree at `(x, y)`, counting from the top left.
+    pub fn get(&self, x: usize, y: usize) -> i8 {
+        self.heights[y * self.width + x]
+    }
+}
+
+/// Ways of surveying a forest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Walk outwards from every tree in each direction until something blocks the view. Trees of the same height
+    /// can't see past each other and there are only ten heights, so this is linear too, just not as obviously.
+    Walk,
+    /// Sweep along each row and column both ways, keeping a stack of the trees that could still block the view.
+    Stack,
+}
+
+/// How many trees can be seen from outside the forest, and the best scenic score of any tree.
+pub fn survey(grid: &Grid, algorithm: Algorithm) -> (usize, usize) {
+    match algorithm {
+        Algorithm::Walk => (solve_part1(grid), solve_part2(grid)),
+        Algorithm::Stack => survey_stack(grid),
+    }
+}
+
+/// The trees seen so far going along a line that could still block the view of the trees to come: each one is
+/// taller than every tree after it, so there's at most one of each height.
+#[derive(Debug, Clone, Copy, Default)]
+struct Skyline {
+    len: usize,
+    trees: [(usize, i8); 10],
+}
+
+impl Skyline {
+    /// Add the tree at `pos` along the line, returning where the tree blocking its view back down the line is.
+    fn see(&mut self, pos: usize, height: i8) -> Option<usize> {
+        while self.len != 0 && self.trees[self.len - 1].1 < height {
+            self.len -= 1;
+        }
+        let blocker = self.len.checked_sub(1).map(|top| self.trees[top]);
+        // A tree as tall as this one and further back can't block anything that this one doesn't.
+        if blocker.is_some_and(|(_, tallest)| tallest == height) {
+            self.len -= 1;
+        }
+        self.trees[self.len] = (pos, height);
+        self.len += 1;
+        blocker.map(|(blocker, _)| blocker)
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// Sweep along each row and column both ways, telling `see` how many trees each tree can see in each direction and
+/// whether it can be seen from outside the forest that way.
+///
+/// Every tree is looked at going [`Direction::Up`] first, then the rows are gone through from the bottom up, with
+/// each tree in a row looked at going [`Direction::Down`], then [`Direction::Left`], then [`Direction::Right`] last.
+fn sweep(grid: &Grid, mut see: impl FnMut(usize, Direction, usize, bool)) {
+    let (width, height) = (grid.width, grid.height);
+
+    // How far the tree at `idx`, `pos` trees along its line, can see back down the line.
+    let mut look = |idx: usize, direction: Direction, pos: usize, skyline: &mut Skyline| match skyline
+        .see(pos, grid.heights[idx])
+    {
+        Some(blocker) => see(idx, direction, pos - blocker, false),
+        None => see(idx, direction, pos, true),
+    };
+
+    // Go down all the columns at once, so as to read the grid in order.
+    let mut skylines = vec![Skyline::default(); width];
+    for y in 0..height {
+        (0..width).for_each(|x| look(y * width + x, Direction::Up, y, &mut skylines[x]));
+    }
+
+    // Then go back up, doing the rest of each row as we get to it.
+    skylines.iter_mut().for_each(Skyline::clear);
+    let mut skyline = Skyline::default();
+    for y in (0..height).rev() {
+        let row = y * width;
+        for (x, skyline) in skylines.iter_mut().enumerate() {
+            look(row + x, Direction::Down, height - 1 - y, skyline);
+        }
+        skyline.clear();
+        (0..width).for_each(|x| look(row + x, Direction::Left, x, &mut skyline));
+        skyline.clear();
+        for x in (0..width).rev() {
+            look(row + x, Direction::Right, width - 1 - x, &mut skyline);
+        }
+    }
+}
+
+fn survey_stack(grid: &Grid) -> (usize, usize) {
+    let mut visibility = bitvec![0; grid.width * grid.height];
+    let mut scores = vec![0; grid.width * grid.height];
+    let mut best = 0;
+
+    // Right comes last, so that's when each tree's score is finished.
+    sweep(grid, |idx, direction, distance, visible| {
+        if visible {
+            visibility.set(idx, true);
+        }
+        match direction {
+            Direction::Up => scores[idx] = distance,
+            Direction::Down | Direction::Left => scores[idx] *= distance,
+            Direction::Right => best = best.max(scores[idx] * distance),
+        }
+    });
+
+    (visibility.count_ones(), best)
+}
+
+fn solve_part1(grid: &Grid) -> usize {
+    let (width, height) = (grid.width, grid.height);
+    let mut visibility = bitvec![0; width * height];
 
     // horizontal lines
-    for y in 0..GRID_SIDE {
+    for y in 0..height {
         let mut tallest = -1;
-        for x in 0..GRID_SIDE {
-            let col = grid[y * GRID_SIDE + x];
+        for x in 0..width {
+            let col = grid.get(x, y);
             if col > tallest {
-                visibility.set(y * GRID_SIDE + x, true);
+                visibility.set(y * width + x, true);
                 tallest = col;
             }
         }
 
         let mut tallest = -1;
-        for x in (0..GRID_SIDE).rev() {
-            let col = grid[y * GRID_SIDE + x];
+        for x in (0..width).rev() {
+            let col = grid.get(x, y);
             if col > tallest {
-                visibility.set(y * GRID_SIDE + x, true);
+                visibility.set(y * width + x, true);
                 tallest = col;
             }
         }
     }
 
     // vertical lines
-    for x in 0..GRID_SIDE {
+    for x in 0..width {
         let mut tallest = -1;
-        for y in 0..GRID_SIDE {
-            let col = grid[y * GRID_SIDE + x];
+        for y in 0..height {
+            let col = grid.get(x, y);
             if col > tallest {
-                visibility.set(y * GRID_SIDE + x, true);
+                visibility.set(y * width + x, true);
                 tallest = col;
             }
         }
 
         let mut tallest = -1;
-        for y in (0..GRID_SIDE).rev() {
-            let col = grid[y * GRID_SIDE + x];
+        for y in (0..height).rev() {
+            let col = grid.get(x, y);
             if col > tallest {
-                visibility.set(y * GRID_SIDE + x, true);
+                visibility.set(y * width + x, true);
                 tallest = col;
             }
         }
@@ -52,19 +242,20 @@ fn solve_part1(grid: &[i8]) -> usize {
     visibility.count_ones()
 }
 
-fn solve_part2(grid: &[i8]) -> usize {
+fn solve_part2(grid: &Grid) -> usize {
+    let width = grid.width;
     let mut winner = 0;
 
-    for y in 0..GRID_SIDE {
-        for x in 0..GRID_SIDE {
-            let height = grid[y * GRID_SIDE + x];
+    for y in 0..grid.height {
+        for x in 0..width {
+            let height = grid.get(x, y);
             let mut score = 1;
             let mut this_dir;
 
             this_dir = 0;
-            for nx in x + 1..GRID_SIDE {
+            for nx in x + 1..width {
                 this_dir += 1;
-                if grid[y * GRID_SIDE + nx] >= height {
+                if grid.get(nx, y) >= height {
                     break;
                 }
             }
@@ -73,16 +264,16 @@ fn solve_part2(grid: &[i8]) -> usize {
             this_dir = 0;
             for nx in (0..x).rev() {
                 this_dir += 1;
-                if grid[y * GRID_SIDE + nx] >= height {
+                if grid.get(nx, y) >= height {
                     break;
                 }
             }
             score *= this_dir;
 
             this_dir = 0;
-            for ny in y + 1..GRID_SIDE {
+            for ny in y + 1..grid.height {
                 this_dir += 1;
-                if grid[ny * GRID_SIDE + x] >= height {
+                if grid.get(x, ny) >= height {
                     break;
                 }
             }
@@ -91,7 +282,7 @@ fn solve_part2(grid: &[i8]) -> usize {
             this_dir = 0;
             for ny in (0..y).rev() {
                 this_dir += 1;
-                if grid[ny * GRID_SIDE + x] >= height {
+                if grid.get(x, ny) >= height {
                     break;
                 }
             }
@@ -105,12 +296,100 @@ fn solve_part2(grid: &[i8]) -> usize {
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    let grid: Vec<i8> = include_str!("input.txt")
-        .bytes()
-        .filter(|ch| ch.is_ascii_digit())
-        .map(|ch| (ch - b'0') as i8)
-        .collect();
-    debug_assert_eq!(grid.len(), GRID_SIDE * GRID_SIDE);
-
-    (solve_part1(&grid), solve_part2(&grid))
+    let grid = include_str!("input.txt").parse::<Grid>().unwrap();
+
+    survey(&grid, Algorithm::Stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+30373
+25512
+65332
+33549
+35390
+";
+
+    #[test]
+    fn test_sample() {
+        let grid = SAMPLE.parse::<Grid>().unwrap();
+        assert_eq!((grid.width(), grid.height()), (5, 5));
+        for algorithm in [Algorithm::Walk, Algorithm::Stack] {
+            assert_eq!(survey(&grid, algorithm), (21, 8));
+        }
+    }
+
+    #[test]
+    fn test_survey() {
+        let survey = Survey::new(&SAMPLE.parse::<Grid>().unwrap());
+        assert_eq!((survey.visible_count(), survey.best_score()), (21, 8));
+
+        let tree = survey.get(2, 3);
+        let distances = Direction::ALL.map(|direction| tree.distance(direction));
+        assert_eq!((tree.height, distances, tree.scenic_score()), (5, [2, 1, 2, 2], 8));
+        let visible_from = Direction::ALL.map(|direction| tree.visible_from(direction));
+        assert_eq!(visible_from, [false, true, true, false]);
+        let tree = survey.get(2, 1);
+        let visible_from = Direction::ALL.map(|direction| tree.visible_from(direction));
+        assert_eq!(visible_from, [true, false, false, true]);
+
+        let pgm = survey.pgm(Heatmap::ScenicScore);
+        assert!(pgm.starts_with(b"P5\n5 5\n255\n"));
+        assert_eq!(pgm[pgm.len() - 25 + 3 * 5 + 2], 255);
+        assert_eq!(survey.pgm(Heatmap::Visibility)[pgm.len() - 25], 127);
+        let ansi = survey.ansi(Heatmap::Visibility);
+        assert_eq!(ansi.lines().count(), 5);
+        assert!(ansi.starts_with("\x1b[38;5;255;48;5;243m3"));
+    }
+
+    #[test]
+    fn test_rectangular() {
+        // The sample without its last column.
+        let narrow = "3037\n2551\n6533\n3354\n3539".parse::<Grid>().unwrap();
+        assert_eq!((narrow.width(), narrow.height()), (4, 5));
+        assert_eq!(solve_part1(&narrow), 18);
+        assert_eq!(solve_part2(&narrow), 4);
+
+        // All 16 trees round the edge, plus the 5s and the 7 in the middle row. The 7 can see 5 trees to its left
+        // and 1 in every other direction.
+        let wide = "3037325\n2551274\n6533213".parse::<Grid>().unwrap();
+        assert_eq!((wide.width(), wide.height()), (7, 3));
+        assert_eq!(solve_part1(&wide), 19);
+        assert_eq!(solve_part2(&wide), 5);
+        assert_eq!(solve_part1(&"1234".parse().unwrap()), 4);
+
+        assert_eq!("123\n45".parse::<Grid>(), Err(ParseGridError::Ragged { line: 2 }));
+        assert_eq!(
+            "123\n4x6".parse::<Grid>(),
+            Err(ParseGridError::BadHeight { line: 2, column: 2 })
+        );
+        assert_eq!("".parse::<Grid>(), Err(ParseGridError::Empty));
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        let mut rng = fastrand::Rng::with_seed(0x2022_0008);
+        for (width, height) in [(1, 1), (1, 7), (7, 1), (13, 8), (40, 40)] {
+            for tallest in [2, 10] {
+                let grid = Grid {
+                    width,
+                    height,
+                    heights: (0..width * height).map(|_| rng.i8(0..tallest)).collect(),
+                };
+                assert_eq!(
+                    survey(&grid, Algorithm::Walk),
+                    survey(&grid, Algorithm::Stack),
+                    "{width}x{height}, heights below {tallest}"
+                );
+                let trees = Survey::new(&grid);
+                assert_eq!(
+                    (trees.visible_count(), trees.best_score()),
+                    survey(&grid, Algorithm::Walk)
+                );
+            }
+        }
+    }
 }
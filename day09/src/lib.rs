@@ -1,9 +1,14 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display, str::FromStr};
 
-use ahash::HashSet;
+mod render;
+mod rope;
+mod stats;
+pub use rope::{Rope, Steps};
+pub use stats::{longest_run, Bounds, Stats};
 
-type Point = (i16, i16);
+pub type Point = (i32, i32);
 
+const PART1_KNOTS: usize = 2;
 const PART2_KNOTS: usize = 10;
 
 fn touching((hx, hy): Point, (tx, ty): Point) -> bool {
@@ -14,74 +19,220 @@ fn next_tail_pos((hx, hy): Point, (tx, ty): Point) -> Point {
     (tx + (hx - tx).signum(), ty + (hy - ty).signum())
 }
 
-fn simulate_part1() -> usize {
-    let mut head = (0, 0);
-    let mut tail = (0, 0);
+/// Which way the head of the rope moves, with up being towards positive y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
 
-    let mut visited = HashSet::default();
-    visited.reserve(7168);
-    visited.insert(tail);
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
 
-    include_str!("input.txt")
-        .lines()
-        .map(|line| line.split_once(' ').unwrap())
-        .for_each(|(dir, amount)| {
-            let (dx, dy) = match dir {
-                "U" => (0, 1),
-                "D" => (0, -1),
-                "L" => (-1, 0),
-                "R" => (1, 0),
-                _ => unreachable!(),
-            };
-            for _ in 0..amount.parse::<u8>().unwrap() {
-                let new_head = (head.0 + dx, head.1 + dy);
-                if !touching(tail, new_head) {
-                    tail = head;
-                }
-                head = new_head;
-                visited.insert(tail);
-            }
-        });
-
-    visited.len()
+    /// How the direction is written in a move list.
+    pub fn code(self) -> &'static str {
+        match self {
+            Direction::Up => "U",
+            Direction::Down => "D",
+            Direction::Left => "L",
+            Direction::Right => "R",
+            Direction::UpLeft => "UL",
+            Direction::UpRight => "UR",
+            Direction::DownLeft => "DL",
+            Direction::DownRight => "DR",
+        }
+    }
+
+    pub fn delta(self) -> Point {
+        match self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, 1),
+            Direction::UpRight => (1, 1),
+            Direction::DownLeft => (-1, -1),
+            Direction::DownRight => (1, -1),
+        }
+    }
+}
+
+/// Move the head `steps` squares in a direction, like `R 4` or `UL 2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub direction: Direction,
+    pub steps: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseMoveError;
+
+impl Display for ParseMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("expected a move like `R 4` or `UL 2`")
+    }
 }
 
-fn simulate_part2() -> usize {
-    let mut knots = [(0, 0); PART2_KNOTS];
+impl Error for ParseMoveError {}
 
-    let mut visited = HashSet::default();
-    visited.reserve(3584);
-    visited.insert(knots.last().copied().unwrap());
+/// A move in a list that couldn't be read, on line `line` counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseMovesError {
+    pub line: usize,
+    pub source: ParseMoveError,
+}
+
+impl Display for ParseMovesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.source)
+    }
+}
+
+impl Error for ParseMovesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (direction, steps) = s.split_once(' ').ok_or(ParseMoveError)?;
+        let direction = Direction::ALL
+            .into_iter()
+            .find(|dir| dir.code() == direction)
+            .ok_or(ParseMoveError)?;
+        let steps = steps.parse().map_err(|_| ParseMoveError)?;
+        Ok(Self { direction, steps })
+    }
+}
 
-    include_str!("input.txt")
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.direction.code(), self.steps)
+    }
+}
+
+/// One move per line.
+pub fn parse_moves(input: &str) -> Result<Vec<Move>, ParseMovesError> {
+    input
         .lines()
-        .map(|line| line.split_once(' ').unwrap())
-        .for_each(|(dir, amount)| {
-            let (dx, dy) = match dir {
-                "U" => (0, 1),
-                "D" => (0, -1),
-                "L" => (-1, 0),
-                "R" => (1, 0),
-                _ => unreachable!(),
-            };
-            for _ in 0..amount.parse::<u8>().unwrap() {
-                let (hx, hy) = knots.first().copied().unwrap();
-                knots[0] = (hx + dx, hy + dy);
-                for i in 1..PART2_KNOTS {
-                    if !touching(knots[i - 1], knots[i]) {
-                        knots[i] = next_tail_pos(knots[i - 1], knots[i]);
-                    } else {
-                        break;
-                    }
-                }
-                visited.insert(knots.last().copied().unwrap());
-            }
-        });
-
-    visited.len()
+        .enumerate()
+        .map(|(idx, line)| line.parse().map_err(|source| ParseMovesError { line: idx + 1, source }))
+        .collect()
+}
+
+/// How many squares the tail of a rope with `knots` knots goes through.
+fn simulate(moves: &[Move], knots: usize) -> usize {
+    // Only the tail gets room set aside, and it can't visit more squares than the head's path covers, nor more
+    // than one per step.
+    let steps = moves.iter().map(|mv| mv.steps as usize).sum::<usize>();
+    let capacity = Bounds::of_moves(moves).area().min(steps + 1);
+    let mut rope = Rope::with_capacity(knots, &[knots - 1], capacity);
+    rope.run(moves);
+    rope.visited(knots - 1).unwrap().len()
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    (simulate_part1(), simulate_part2())
+    let moves = parse_moves(include_str!("input.txt")).unwrap();
+    (simulate(&moves, PART1_KNOTS), simulate(&moves, PART2_KNOTS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2\n";
+    const LARGER_SAMPLE: &str = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20\n";
+
+    #[test]
+    fn test_samples() {
+        let moves = parse_moves(SAMPLE).unwrap();
+        assert_eq!(simulate(&moves, PART1_KNOTS), 13);
+        assert_eq!(simulate(&moves, PART2_KNOTS), 1);
+        assert_eq!(simulate(&parse_moves(LARGER_SAMPLE).unwrap(), PART2_KNOTS), 36);
+
+        assert_eq!(
+            parse_moves("R 4\nX 1"),
+            Err(ParseMovesError {
+                line: 2,
+                source: ParseMoveError
+            })
+        );
+        assert_eq!("R".parse::<Move>(), Err(ParseMoveError));
+    }
+
+    #[test]
+    fn test_steps() {
+        let moves = parse_moves("UR 3\nL 2").unwrap();
+        let mut rope = Rope::new(3, &[0, 1, 2]);
+        let mut steps = rope.steps(&moves);
+        assert_eq!(steps.next(), Some(Direction::UpRight));
+        assert_eq!(steps.rope().knots(), [(1, 1), (0, 0), (0, 0)]);
+        assert_eq!(steps.by_ref().count(), 4);
+        assert_eq!(steps.rope().knots(), [(1, 3), (2, 2), (1, 1)]);
+
+        assert_eq!(rope.visited(0).unwrap().len(), 6);
+        assert_eq!(rope.visited(1).unwrap().len(), 3);
+        assert_eq!(rope.visited(2).unwrap().len(), 2);
+
+        let mut rope = Rope::with_capacity(3, &[2], 100);
+        assert!(rope.visited(2).unwrap().capacity() >= 100);
+        rope.run(&moves);
+        assert_eq!(rope.visited(0), None);
+        assert_eq!(rope.visited(2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let moves = parse_moves(SAMPLE).unwrap();
+        let bounds = Bounds::of_moves(&moves);
+        assert_eq!((bounds.min, bounds.max), ((0, 0), (5, 4)));
+
+        let mut rope = Rope::new(PART1_KNOTS, &[0, 1]);
+        rope.run(&moves);
+        assert_eq!(rope.render(&bounds), "......\n......\n.1H...\n......\ns.....\n");
+        assert_eq!(
+            rope.render_visited(1, &bounds),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+        let pgm = rope.visited_pgm(1, &bounds);
+        assert!(pgm.starts_with(b"P5\n6 5\n255\n"));
+        assert_eq!(pgm.iter().rev().take(30).filter(|&&px| px == 255).count(), 13);
+
+        let stats = Stats::new(&rope, &moves);
+        assert_eq!(stats.visited, [(0, 21), (1, 13)]);
+        assert_eq!(stats.longest_run.unwrap().to_string(), "L 5");
+        assert_eq!(
+            stats.to_string(),
+            "bounds: (0, 0) to (5, 4) (6x5)\n\
+             knot 0 visited 21 positions\n\
+             knot 1 visited 13 positions\n\
+             longest run: L 5\n"
+        );
+        assert_eq!(
+            longest_run(&parse_moves("R 2\nR 3\nU 4").unwrap()),
+            Some(Move {
+                direction: Direction::Right,
+                steps: 5
+            })
+        );
+    }
 }
@@ -0,0 +1,51 @@
+use crate::{Bounds, Point, Rope};
+
+/// Draw every position within `bounds` as a character, top row first.
+fn draw(bounds: &Bounds, mut cell: impl FnMut(Point) -> char) -> String {
+    let mut out = String::with_capacity((bounds.width() + 1) * bounds.height());
+    for y in (bounds.min.1..=bounds.max.1).rev() {
+        out.extend((bounds.min.0..=bounds.max.0).map(|x| cell((x, y))));
+        out.push('\n');
+    }
+    out
+}
+
+impl Rope {
+    /// Draw the rope like the puzzle does: `H` for the head, then `1`, `2` and so on for the knots behind it
+    /// (`a` to `z` after `9`), `s` for the start and `.` for empty space. Where knots overlap, the one nearest
+    /// the head is shown.
+    pub fn render(&self, bounds: &Bounds) -> String {
+        draw(bounds, |point| {
+            match self.knots().iter().position(|&knot| knot == point) {
+                Some(0) => 'H',
+                Some(knot) => char::from_digit(knot as u32, 36).unwrap_or('*'),
+                None if point == (0, 0) => 's',
+                None => '.',
+            }
+        })
+    }
+
+    /// Draw everywhere knot number `knot` has been as `#`, with the start as `s`. Nothing is drawn for a knot that
+    /// isn't being tracked.
+    pub fn render_visited(&self, knot: usize, bounds: &Bounds) -> String {
+        draw(bounds, |point| match point {
+            (0, 0) => 's',
+            point if self.visited(knot).is_some_and(|visited| visited.contains(&point)) => '#',
+            _ => '.',
+        })
+    }
+
+    /// Everywhere knot number `knot` has been as a binary PGM image, white on black, one pixel per position.
+    pub fn visited_pgm(&self, knot: usize, bounds: &Bounds) -> Vec<u8> {
+        let levels = (bounds.min.1..=bounds.max.1).rev().flat_map(|y| {
+            (bounds.min.0..=bounds.max.0).map(move |x| {
+                if self.visited(knot).is_some_and(|visited| visited.contains(&(x, y))) {
+                    255
+                } else {
+                    0
+                }
+            })
+        });
+        aoc_pgm::encode(bounds.width(), bounds.height(), levels)
+    }
+}
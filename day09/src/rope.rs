@@ -0,0 +1,124 @@
+use ahash::HashSet;
+
+use crate::{next_tail_pos, touching, Direction, Move, Point};
+
+/// A rope made of knots, each one following the one before it, which remembers everywhere some of the knots have
+/// been.
+#[derive(Debug, Clone)]
+pub struct Rope {
+    knots: Vec<Point>,
+    /// `None` for knots that aren't being tracked.
+    visited: Vec<Option<HashSet<Point>>>,
+}
+
+impl Rope {
+    /// A rope with `len` knots, all starting at the origin, remembering where the knots in `track` have been,
+    /// counting the head as knot 0.
+    pub fn new(len: usize, track: &[usize]) -> Self {
+        Self::with_capacity(len, track, 0)
+    }
+
+    /// A rope with room for each tracked knot to visit `capacity` positions before it needs to allocate again.
+    pub fn with_capacity(len: usize, track: &[usize], capacity: usize) -> Self {
+        assert!(len != 0, "a rope needs at least one knot");
+        let mut visited = vec![None; len];
+        for &knot in track {
+            assert!(knot < len, "can't track knot {knot} of a rope with {len} knots");
+            let mut set = HashSet::with_capacity_and_hasher(capacity, Default::default());
+            set.insert((0, 0));
+            visited[knot] = Some(set);
+        }
+        Self {
+            knots: vec![(0, 0); len],
+            visited,
+        }
+    }
+
+    /// Every knot, starting from the head.
+    pub fn knots(&self) -> &[Point] {
+        &self.knots
+    }
+
+    pub fn head(&self) -> Point {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Point {
+        *self.knots.last().unwrap()
+    }
+
+    /// Every position knot number `knot` has been in, counting the head as knot 0, or `None` if it isn't being
+    /// tracked.
+    pub fn visited(&self, knot: usize) -> Option<&HashSet<Point>> {
+        self.visited[knot].as_ref()
+    }
+
+    /// Move the head one square, and let the rest of the rope catch up.
+    pub fn step(&mut self, direction: Direction) {
+        let (dx, dy) = direction.delta();
+        let (hx, hy) = self.knots[0];
+        self.knots[0] = (hx + dx, hy + dy);
+        if let Some(visited) = &mut self.visited[0] {
+            visited.insert(self.knots[0]);
+        }
+        for i in 1..self.knots.len() {
+            // A knot that stays put doesn't pull on the ones after it.
+            if touching(self.knots[i - 1], self.knots[i]) {
+                break;
+            }
+            self.knots[i] = next_tail_pos(self.knots[i - 1], self.knots[i]);
+            if let Some(visited) = &mut self.visited[i] {
+                visited.insert(self.knots[i]);
+            }
+        }
+    }
+
+    /// Break moves down into single steps of the head, pulling the rest of the rope along after each. See
+    /// [`Steps::rope`] for where the knots are in between.
+    pub fn steps<'a, I: IntoIterator<Item = &'a Move>>(&mut self, moves: I) -> Steps<'_, 'a, I::IntoIter> {
+        Steps {
+            rope: self,
+            moves: moves.into_iter(),
+            current: None,
+        }
+    }
+
+    pub fn run<'a>(&mut self, moves: impl IntoIterator<Item = &'a Move>) {
+        self.steps(moves).for_each(drop);
+    }
+}
+
+/// An iterator that breaks moves down into single-square steps of the head, yielding the direction of each one once
+/// every knot has caught up, so the rope can be drawn or measured between steps.
+pub struct Steps<'r, 'm, I> {
+    rope: &'r mut Rope,
+    moves: I,
+    /// The move being carried out, and how many steps of it are left.
+    current: Option<(&'m Move, u32)>,
+}
+
+impl<I> Steps<'_, '_, I> {
+    /// The rope as it is after the last step, partway through a move if need be.
+    pub fn rope(&self) -> &Rope {
+        self.rope
+    }
+}
+
+impl<'m, I: Iterator<Item = &'m Move>> Iterator for Steps<'_, 'm, I> {
+    type Item = Direction;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (mv, left) = loop {
+            match self.current {
+                Some((mv, left)) if left != 0 => break (mv, left),
+                _ => {
+                    let mv = self.moves.next()?;
+                    self.current = Some((mv, mv.steps));
+                }
+            }
+        };
+        self.current = Some((mv, left - 1));
+        self.rope.step(mv.direction);
+        Some(mv.direction)
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::Display;
+
+use crate::{Move, Point, Rope};
+
+/// The smallest rectangle holding a set of points, corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    /// Everywhere the head goes while carrying out some moves from the origin. The other knots only ever move
+    /// towards the knot in front of them, so they stay in here too.
+    pub fn of_moves<'a>(moves: impl IntoIterator<Item = &'a Move>) -> Self {
+        let mut bounds = Self::default();
+        let mut head = (0, 0);
+        for mv in moves {
+            let (dx, dy) = mv.direction.delta();
+            let steps = mv.steps as i32;
+            head = (head.0 + dx * steps, head.1 + dy * steps);
+            bounds.include(head);
+        }
+        bounds
+    }
+
+    pub fn include(&mut self, (x, y): Point) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    pub fn contains(&self, (x, y): Point) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    pub fn width(&self) -> usize {
+        self.min.0.abs_diff(self.max.0) as usize + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.min.1.abs_diff(self.max.1) as usize + 1
+    }
+
+    pub fn area(&self) -> usize {
+        self.width() * self.height()
+    }
+}
+
+impl Default for Bounds {
+    /// Just the origin.
+    fn default() -> Self {
+        Self {
+            min: (0, 0),
+            max: (0, 0),
+        }
+    }
+}
+
+/// The longest the head goes in a straight line, joining up moves that go the same way one after the other.
+pub fn longest_run<'a>(moves: impl IntoIterator<Item = &'a Move>) -> Option<Move> {
+    let mut longest: Option<Move> = None;
+    let mut current: Option<Move> = None;
+    for &mv in moves {
+        current = match current {
+            Some(run) if run.direction == mv.direction => Some(Move {
+                steps: run.steps + mv.steps,
+                ..run
+            }),
+            _ => Some(mv),
+        };
+        if longest.is_none_or(|longest| current.unwrap().steps > longest.steps) {
+            longest = current;
+        }
+    }
+    longest
+}
+
+/// A summary of how a rope moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub bounds: Bounds,
+    /// How many positions each tracked knot has been in, starting from the one nearest the head.
+    pub visited: Vec<(usize, usize)>,
+    pub longest_run: Option<Move>,
+}
+
+impl Stats {
+    /// Sum up a rope that has carried out `moves`.
+    pub fn new(rope: &Rope, moves: &[Move]) -> Self {
+        Self {
+            bounds: Bounds::of_moves(moves),
+            visited: (0..rope.knots().len())
+                .filter_map(|knot| Some((knot, rope.visited(knot)?.len())))
+                .collect(),
+            longest_run: longest_run(moves),
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Bounds { min, max } = self.bounds;
+        writeln!(
+            f,
+            "bounds: {min:?} to {max:?} ({}x{})",
+            self.bounds.width(),
+            self.bounds.height()
+        )?;
+        for (knot, visited) in &self.visited {
+            writeln!(f, "knot {knot} visited {visited} positions")?;
+        }
+        match self.longest_run {
+            Some(run) => writeln!(f, "longest run: {run}"),
+            None => writeln!(f, "longest run: none"),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    num::ParseIntError,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    /// Add to [`Register::X`].
+    Addx(i64),
+}
+
+/// What an instruction does, leaving out its arguments. New instructions need a kind here, with a cost in
+/// [`InstructionKind::default_cost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstructionKind {
+    Noop,
+    Addx,
+}
+
+impl InstructionKind {
+    pub const ALL: [InstructionKind; 2] = [InstructionKind::Noop, InstructionKind::Addx];
+
+    /// How many cycles the instruction takes on the handheld's CPU.
+    pub fn default_cost(self) -> usize {
+        match self {
+            InstructionKind::Noop => 1,
+            InstructionKind::Addx => 2,
+        }
+    }
+}
+
+impl Instruction {
+    pub fn kind(self) -> InstructionKind {
+        match self {
+            Instruction::Noop => InstructionKind::Noop,
+            Instruction::Addx(_) => InstructionKind::Addx,
+        }
+    }
+
+    /// How many cycles the instruction takes on a CPU with these costs.
+    pub fn cost(self, costs: &Costs) -> usize {
+        costs.get(self.kind())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseInstructionError {
+    /// Neither `noop` on its own nor `addx` with an argument.
+    UnknownInstruction,
+    /// `addx` with something other than a whole number to add.
+    BadValue(ParseIntError),
+}
+
+impl Display for ParseInstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseInstructionError::UnknownInstruction => f.write_str("expected `noop` or `addx` and a number"),
+            ParseInstructionError::BadValue(_) => f.write_str("`addx` needs a whole number"),
+        }
+    }
+}
+
+impl Error for ParseInstructionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseInstructionError::UnknownInstruction => None,
+            ParseInstructionError::BadValue(source) => Some(source),
+        }
+    }
+}
+
+/// An instruction that couldn't be read, at line `line` of the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseProgramError {
+    pub line: usize,
+    pub source: ParseInstructionError,
+}
+
+impl Display for ParseProgramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.source)
+    }
+}
+
+impl Error for ParseProgramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseInstructionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ') {
+            None if s == "noop" => Ok(Instruction::Noop),
+            Some(("addx", value)) => value
+                .parse()
+                .map(Instruction::Addx)
+                .map_err(ParseInstructionError::BadValue),
+            _ => Err(ParseInstructionError::UnknownInstruction),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(value) => write!(f, "addx {value}"),
+        }
+    }
+}
+
+/// One instruction per line.
+pub fn parse_program(input: &str) -> Result<Vec<Instruction>, ParseProgramError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            line.parse()
+                .map_err(|source| ParseProgramError { line: idx + 1, source })
+        })
+        .collect()
+}
+
+/// How many cycles each kind of instruction takes. Starts out with every kind's [`InstructionKind::default_cost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Costs([usize; InstructionKind::ALL.len()]);
+
+impl Default for Costs {
+    fn default() -> Self {
+        Self(InstructionKind::ALL.map(InstructionKind::default_cost))
+    }
+}
+
+impl Costs {
+    /// Make one kind of instruction take `cycles` cycles instead.
+    pub fn with(mut self, kind: InstructionKind, cycles: usize) -> Self {
+        self.0[kind as usize] = cycles;
+        self
+    }
+
+    pub fn get(&self, kind: InstructionKind) -> usize {
+        self.0[kind as usize]
+    }
+}
+
+/// The CPU's registers. There's only X for now; another one needs a variant here and a starting value in
+/// [`Register::initial`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    X,
+}
+
+impl Register {
+    pub const ALL: [Register; 1] = [Register::X];
+
+    pub fn initial(self) -> i64 {
+        match self {
+            Register::X => 1,
+        }
+    }
+}
+
+/// Every register's value, looked up by indexing with a [`Register`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registers([i64; Register::ALL.len()]);
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self(Register::ALL.map(Register::initial))
+    }
+}
+
+impl Index<Register> for Registers {
+    type Output = i64;
+
+    fn index(&self, register: Register) -> &Self::Output {
+        &self.0[register as usize]
+    }
+}
+
+impl IndexMut<Register> for Registers {
+    fn index_mut(&mut self, register: Register) -> &mut Self::Output {
+        &mut self.0[register as usize]
+    }
+}
+
+/// Something that wants to know what the CPU is up to.
+pub trait Hook {
+    /// Called during every cycle, numbered from 1, with the registers as they are before the instruction being
+    /// carried out has finished.
+    fn during(&mut self, cycle: usize, registers: &Registers);
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Cpu {
+    registers: Registers,
+    cycle: usize,
+    costs: Costs,
+}
+
+impl Cpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_costs(costs: Costs) -> Self {
+        Self {
+            costs,
+            ..Self::default()
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// How many cycles have gone by.
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn execute(&mut self, instruction: Instruction, hooks: &mut [&mut dyn Hook]) {
+        for _ in 0..instruction.cost(&self.costs) {
+            self.cycle += 1;
+            hooks
+                .iter_mut()
+                .for_each(|hook| hook.during(self.cycle, &self.registers));
+        }
+        match instruction {
+            Instruction::Noop => {}
+            Instruction::Addx(value) => self.registers[Register::X] += value,
+        }
+    }
+
+    pub fn run(&mut self, program: &[Instruction], hooks: &mut [&mut dyn Hook]) {
+        program.iter().for_each(|&instruction| self.execute(instruction, hooks));
+    }
+}
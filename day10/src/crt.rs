@@ -0,0 +1,107 @@
+use std::fmt::Display;
+
+use aoc_ocr::OcrError;
+
+use crate::{Hook, Register, Registers};
+
+/// A screen that draws one pixel per cycle, left to right and then top to bottom, lighting it up if it's under the
+/// sprite centred on X. Once the last pixel has been drawn the beam goes back to the top and starts over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crt {
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+    pixels: Vec<bool>,
+    cycles: usize,
+}
+
+impl Crt {
+    /// A `width` by `height` screen with a three pixel wide sprite.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width != 0 && height != 0, "a screen needs at least one pixel");
+        Self {
+            width,
+            height,
+            sprite_width: 3,
+            pixels: vec![false; width * height],
+            cycles: 0,
+        }
+    }
+
+    /// Make the sprite `sprite_width` pixels wide. If that's an even number, X is the pixel just left of the
+    /// middle.
+    pub fn sprite_width(self, sprite_width: usize) -> Self {
+        Self { sprite_width, ..self }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Every pixel, top row first.
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// How many times the whole screen has been drawn. Anything left over has drawn over the top of the last
+    /// frame; if this is zero, the pixels that haven't been reached yet are dark.
+    pub fn frames(&self) -> usize {
+        self.cycles / self.pixels.len()
+    }
+
+    /// Read the letters drawn on the screen.
+    pub fn decode(&self) -> Result<String, OcrError> {
+        aoc_ocr::decode(&self.pixels, self.width)
+    }
+
+    /// The screen as a binary PBM image, with lit pixels white.
+    pub fn pbm(&self) -> Vec<u8> {
+        let mut out = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.pixels.chunks(self.width) {
+            // Each row starts on a new byte, and a set bit is black.
+            out.extend(row.chunks(8).map(|byte| {
+                byte.iter()
+                    .enumerate()
+                    .fold(0, |bits, (idx, &lit)| bits | (!lit as u8) << (7 - idx))
+            }));
+        }
+        out
+    }
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self::new(40, 6)
+    }
+}
+
+impl Hook for Crt {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        // If the sprite overlaps with the pixel currently being drawn then we should color in that pixel,
+        // otherwise leave it blank.
+        let pos = (cycle - 1) % self.pixels.len();
+        let column = (pos % self.width) as i64;
+        let left = registers[Register::X] - (self.sprite_width as i64 - 1) / 2;
+        self.pixels[pos] = (left..left + self.sprite_width as i64).contains(&column);
+        self.cycles = self.cycles.max(cycle);
+    }
+}
+
+impl Display for Crt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.pixels.chunks(self.width) {
+            row.iter()
+                .try_for_each(|&lit| write!(f, "{}", if lit { '█' } else { ' ' }))?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
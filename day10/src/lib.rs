@@ -1,61 +1,173 @@
 use std::fmt::Display;
 
-const SCREEN_WIDTH: usize = 40;
-const SCREEN_HEIGHT: usize = 6;
-
-struct Screen([i8; SCREEN_WIDTH * SCREEN_HEIGHT]);
-
-impl Display for Screen {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut it = self.0.into_iter().enumerate();
-        for _y in 0..SCREEN_HEIGHT {
-            // If, at each clock cycle, the sprite overlapped with the pixel currently being drawn
-            // then we should color in that pixel, otherwise leave it blank.
-            it.by_ref().take(SCREEN_WIDTH).try_for_each(|(clk, x)| {
-                write!(
-                    f,
-                    "{}",
-                    if (x - 1..=x + 1).contains(&((clk % 40) as i8)) {
-                        '█'
-                    } else {
-                        ' '
-                    }
-                )
-            })?;
-            writeln!(f)?;
+mod cpu;
+mod crt;
+pub use cpu::{
+    parse_program, Costs, Cpu, Hook, Instruction, InstructionKind, ParseInstructionError, ParseProgramError, Register,
+    Registers,
+};
+pub use crt::Crt;
+
+/// Adds up the signal strength (the cycle number times X) during the 20th cycle and every 40 cycles after that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalStrength {
+    first: usize,
+    every: usize,
+    pub total: i64,
+}
+
+impl SignalStrength {
+    pub fn new(first: usize, every: usize) -> Self {
+        Self { first, every, total: 0 }
+    }
+}
+
+impl Default for SignalStrength {
+    fn default() -> Self {
+        Self::new(20, 40)
+    }
+}
+
+impl Hook for SignalStrength {
+    fn during(&mut self, cycle: usize, registers: &Registers) {
+        if cycle >= self.first && (cycle - self.first).is_multiple_of(self.every) {
+            self.total += cycle as i64 * registers[Register::X];
         }
-        Ok(())
     }
 }
 
 #[inline]
 pub fn solve() -> (impl Display, impl Display) {
-    // Execute the program, storing the values in each clock cycle into an array.
-    let mut x: i8 = 1;
-    let mut values = [0; SCREEN_WIDTH * SCREEN_HEIGHT];
-    let mut it = values.iter_mut();
-    include_str!("input.txt").lines().for_each(|line| {
-        // It always takes at least one clock cycle to execute an instruction, during which the
-        // value of X will remain the same. Therefore, the next clock cycle's values of X will be
-        // the curren tone.
-        *it.next().unwrap() = x;
-        // Then, if this instruction is an addx, we'll spend one more cycle doing some internal
-        // work to increment the value of X, during which the value of X will not vary.
-        if line != "noop" {
-            *it.next().unwrap() = x;
-            x += line["addx ".len()..].parse::<i8>().unwrap();
+    let program = parse_program(include_str!("input.txt")).unwrap();
+
+    let mut signal_strength = SignalStrength::default();
+    let mut crt = Crt::default();
+    Cpu::new().run(&program, &mut [&mut signal_strength, &mut crt]);
+
+    // If the screen shows something other than letters, show the screen itself so that it can be read by eye.
+    (signal_strength.total, crt.decode().unwrap_or_else(|_| crt.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu() {
+        let program = parse_program("noop\naddx 3\naddx -5").unwrap();
+        let mut seen = Vec::new();
+        struct Record<'a>(&'a mut Vec<(usize, i64)>);
+        impl Hook for Record<'_> {
+            fn during(&mut self, cycle: usize, registers: &Registers) {
+                self.0.push((cycle, registers[Register::X]));
+            }
         }
-    });
-
-    // Compute the signal strength by taking every (20 + 40n)th value
-    let signal_strength: i16 = values
-        .iter()
-        .enumerate()
-        .skip(19)
-        .step_by(40)
-        .map(|(clk, &x)| (clk + 1) as i16 * x as i16)
-        .sum();
-
-    // Return the signal strength + the Screen, which knows how to Display itself.
-    (signal_strength, Screen(values))
+
+        let mut cpu = Cpu::new();
+        cpu.run(&program, &mut [&mut Record(&mut seen)]);
+        assert_eq!(seen, [(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+        assert_eq!((cpu.cycle(), cpu.registers()[Register::X]), (5, -1));
+
+        let costs = Costs::default()
+            .with(InstructionKind::Noop, 2)
+            .with(InstructionKind::Addx, 1);
+        assert_eq!(Instruction::Addx(3).cost(&Costs::default()), 2);
+        assert_eq!(Instruction::Addx(3).cost(&costs), 1);
+        let mut cpu = Cpu::with_costs(costs);
+        let mut strength = SignalStrength::new(2, 2);
+        cpu.run(&program, &mut [&mut strength]);
+        assert_eq!(cpu.cycle(), 4);
+        // X is 1 during cycle 2 and 4 during cycle 4.
+        assert_eq!(strength.total, 2 + 4 * 4);
+
+        assert!(matches!(
+            parse_program("noop\naddx x"),
+            Err(ParseProgramError {
+                line: 2,
+                source: ParseInstructionError::BadValue(_)
+            })
+        ));
+        assert_eq!(
+            parse_program("noop\nnoop 1"),
+            Err(ParseProgramError {
+                line: 2,
+                source: ParseInstructionError::UnknownInstruction
+            })
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        // Keep the sprite over the first three columns and then move it off the screen, well before the end.
+        let program = parse_program("noop\nnoop\nnoop\naddx 100").unwrap();
+        let mut crt = Crt::default();
+        Cpu::new().run(&program, &mut [&mut crt]);
+        let first_row = crt.to_string().lines().next().unwrap().to_owned();
+        assert_eq!(first_row, format!("███{}", " ".repeat(37)));
+        assert_eq!(crt.frames(), 0);
+
+        // A two pixel sprite covers X and the pixel to its right.
+        let mut crt = Crt::new(4, 2).sprite_width(2);
+        let mut cpu = Cpu::new();
+        cpu.run(
+            &parse_program("noop\nnoop\nnoop\nnoop\naddx -1\naddx 0").unwrap(),
+            &mut [&mut crt],
+        );
+        assert_eq!(crt.to_string(), " ██ \n █  \n");
+        assert_eq!(crt.frames(), 1);
+
+        // Carrying on draws over the first frame.
+        cpu.execute(Instruction::Addx(0), &mut [&mut crt]);
+        assert_eq!(crt.to_string(), "███ \n █  \n");
+        assert_eq!((crt.frames(), crt.get(0, 0), crt.get(0, 1)), (1, true, false));
+        assert_eq!(crt.pbm(), b"P4\n4 2\n\x10\xb0");
+    }
+
+    #[test]
+    fn test_decode() {
+        let drawing = "\
+####.#..#.####.####.####.#..#..##..####.
+#....#..#....#.#.......#.#..#.#..#....#.
+###..####...#..###....#..####.#......#..
+#....#..#..#...#.....#...#..#.#.....#...
+#....#..#.#....#....#....#..#.#..#.#....
+####.#..#.####.#....####.#..#..##..####.
+";
+        // Each addx lasts for two pixels, so put the sprite wherever lights up just the right ones out of each pair.
+        let pixels = drawing
+            .lines()
+            .flat_map(str::chars)
+            .map(|c| c == '#')
+            .collect::<Vec<_>>();
+        let xs = pixels
+            .chunks(2)
+            .enumerate()
+            .map(|(idx, pair)| {
+                let column = (idx * 2 % 40) as i64;
+                match pair {
+                    [true, true] => column + 1,
+                    [true, false] => column - 1,
+                    [false, true] => column + 2,
+                    _ => column + 3,
+                }
+            })
+            .chain([0])
+            .collect::<Vec<_>>();
+        let program = xs
+            .windows(2)
+            .map(|w| Instruction::Addx(w[1] - w[0]))
+            .collect::<Vec<_>>();
+
+        let mut crt = Crt::default();
+        Cpu::new().run(&program, &mut [&mut crt]);
+        assert_eq!(crt.to_string(), drawing.replace('#', "█").replace('.', " "));
+        assert_eq!(crt.decode().unwrap(), "EHZFZHCZ");
+
+        let mut crt = Crt::default();
+        Cpu::new().run(&parse_program("noop\naddx 100").unwrap(), &mut [&mut crt]);
+        assert!(matches!(
+            crt.decode(),
+            Err(aoc_ocr::OcrError::UnknownGlyph { index: 0, .. })
+        ));
+    }
 }
@@ -0,0 +1,74 @@
+use ahash::AHashMap;
+
+use crate::{sim::modulus, Monkey, SimulationError};
+
+/// How many times an item can be inspected before we give up waiting for it to go round a loop. Without relief
+/// it's bound to sooner or later, but with relief its worry level can keep creeping up for as long as it likes.
+pub const MAX_PATH: usize = 1 << 20;
+
+/// How many items each monkey inspects in `rounds` rounds, without playing them all out.
+///
+/// Items never affect each other, so each one is followed on its own from monkey to monkey. Without relief its
+/// worry level is kept modulo the product of the tests, so sooner or later it's back at a monkey with a worry level
+/// it's had there before, and from then on it goes round the same loop, getting through the same number of
+/// rounds each time. With relief worry levels are exact, and usually settle into a loop too; if an item hasn't
+/// after [`MAX_PATH`] inspections, it's given up on with [`SimulationError::NoLoop`].
+pub fn inspections(monkeys: &[Monkey], rounds: u64, relief: bool) -> Result<Vec<u64>, SimulationError> {
+    let modulus = modulus(monkeys, relief)?;
+    let mut inspected = vec![0; monkeys.len()];
+
+    // Where each state, a monkey about to inspect the item and its worry level, was first seen along the path.
+    let mut seen = AHashMap::new();
+    // The monkey doing each inspection, and the round it happens in.
+    let mut path = Vec::new();
+
+    for (start, item) in monkeys
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, monkey)| monkey.items.iter().map(move |&item| (idx, item)))
+    {
+        seen.clear();
+        path.clear();
+        let (mut monkey, mut worry, mut round) = (start, item, 0);
+        let loop_start = loop {
+            if round >= rounds {
+                break path.len();
+            }
+            if let Some(&first) = seen.get(&(monkey, worry)) {
+                break first;
+            }
+            if path.len() == MAX_PATH {
+                return Err(SimulationError::NoLoop {
+                    monkey: start,
+                    worry: item,
+                });
+            }
+            seen.insert((monkey, worry), path.len());
+            path.push((monkey, round));
+
+            let (next, target) = monkeys[monkey]
+                .throw(&worry, relief, modulus)
+                .ok_or(SimulationError::Arithmetic { monkey })?;
+            // Monkeys take their turns in order, so throwing to one that's already had its turn means waiting for
+            // the next round.
+            if target <= monkey {
+                round += 1;
+            }
+            (monkey, worry) = (target, next);
+        };
+
+        for &(monkey, _) in &path[..loop_start] {
+            inspected[monkey] += 1;
+        }
+        if let Some(&(_, first_round)) = path.get(loop_start) {
+            // Every time round the loop takes the same number of rounds, which is at least one since a monkey can
+            // only see an item once a round.
+            let period = round - first_round;
+            for &(monkey, start) in &path[loop_start..] {
+                inspected[monkey] += (rounds - 1 - start) / period + 1;
+            }
+        }
+    }
+
+    Ok(inspected)
+}
@@ -0,0 +1,226 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    iter::Peekable,
+    str::{CharIndices, FromStr},
+};
+
+use num_bigint::BigUint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn symbol(self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+        }
+    }
+}
+
+/// How a monkey works out the new worry level from the old one, like `old * 19` or `(old + 3) / 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Old,
+    Const(u64),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+
+impl Expr {
+    /// The new worry level, or `None` if working it out overflows, goes below zero or divides by zero.
+    pub fn eval(&self, old: u64) -> Option<u64> {
+        match self {
+            Expr::Old => Some(old),
+            Expr::Const(n) => Some(*n),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(old)?, rhs.eval(old)?);
+                match op {
+                    BinOp::Add => lhs.checked_add(rhs),
+                    BinOp::Sub => lhs.checked_sub(rhs),
+                    BinOp::Mul => lhs.checked_mul(rhs),
+                    BinOp::Div => lhs.checked_div(rhs),
+                }
+            }
+        }
+    }
+
+    /// The new worry level, or `None` if working it out goes below zero or divides by zero.
+    pub fn eval_big(&self, old: &BigUint) -> Option<BigUint> {
+        match self {
+            Expr::Old => Some(old.clone()),
+            Expr::Const(n) => Some(BigUint::from(*n)),
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval_big(old)?, rhs.eval_big(old)?);
+                match op {
+                    BinOp::Add => Some(lhs + rhs),
+                    BinOp::Sub => (lhs >= rhs).then(|| lhs - rhs),
+                    BinOp::Mul => Some(lhs * rhs),
+                    BinOp::Div => (rhs != BigUint::ZERO).then(|| lhs / rhs),
+                }
+            }
+        }
+    }
+
+    /// Whether working the expression out modulo some number gives the same answers as [`Expr::eval`], which rules
+    /// out division, and subtraction too since it'd wrap around where `eval` goes below zero.
+    pub fn is_modular(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Const(_) => true,
+            Expr::Binary(lhs, op, rhs) => matches!(op, BinOp::Add | BinOp::Mul) && lhs.is_modular() && rhs.is_modular(),
+        }
+    }
+
+    /// The new worry level modulo `modulus`, given the old one modulo `modulus`. Unlike [`Expr::eval`] nothing
+    /// can overflow.
+    ///
+    /// # Panics
+    ///
+    /// If the expression subtracts or divides; see [`Expr::is_modular`].
+    pub fn eval_mod(&self, old: u64, modulus: u64) -> u64 {
+        match self {
+            Expr::Old => old,
+            Expr::Const(n) => n % modulus,
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval_mod(old, modulus), rhs.eval_mod(old, modulus));
+                match op {
+                    BinOp::Add => ((lhs as u128 + rhs as u128) % modulus as u128) as u64,
+                    BinOp::Mul => ((lhs as u128 * rhs as u128) % modulus as u128) as u64,
+                    BinOp::Sub | BinOp::Div => panic!("`{}` can't be done modulo {modulus}", op.symbol()),
+                }
+            }
+        }
+    }
+}
+
+/// Written out with every operation that's part of a bigger one in parentheses.
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operand = |f: &mut std::fmt::Formatter<'_>, expr: &Expr| match expr {
+            Expr::Binary(..) => write!(f, "({expr})"),
+            _ => write!(f, "{expr}"),
+        };
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Const(n) => write!(f, "{n}"),
+            Expr::Binary(lhs, op, rhs) => {
+                operand(f, lhs)?;
+                write!(f, " {} ", op.symbol())?;
+                operand(f, rhs)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseExprError {
+    /// Where the expression stopped making sense, counting from 1. One past the end if it stopped early.
+    pub column: usize,
+}
+
+impl Display for ParseExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "column {}: expected `old`, a number, an operator or a parenthesis",
+            self.column
+        )
+    }
+}
+
+impl Error for ParseExprError {}
+
+/// A recursive descent parser where `*` and `/` bind tighter than `+` and `-`, and everything is left associative.
+struct Parser<'a> {
+    s: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// The next character that isn't whitespace, with its byte offset.
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn error(&mut self) -> ParseExprError {
+        let offset = self.peek().map_or(self.s.len(), |(offset, _)| offset);
+        ParseExprError {
+            column: self.s[..offset].chars().count() + 1,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[BinOp],
+        operand: fn(&mut Self) -> Result<Expr, ParseExprError>,
+    ) -> Result<Expr, ParseExprError> {
+        let mut lhs = operand(self)?;
+        while let Some(op) = self
+            .peek()
+            .and_then(|(_, c)| ops.iter().copied().find(|op| op.symbol() == c))
+        {
+            self.chars.next();
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(operand(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseExprError> {
+        self.binary(&[BinOp::Add, BinOp::Sub], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseExprError> {
+        self.binary(&[BinOp::Mul, BinOp::Div], Self::atom)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseExprError> {
+        match self.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let inner = self.expr()?;
+                match self.peek() {
+                    Some((_, ')')) => {
+                        self.chars.next();
+                        Ok(inner)
+                    }
+                    _ => Err(self.error()),
+                }
+            }
+            Some((start, c)) if c.is_ascii_alphanumeric() => {
+                while self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric()).is_some() {}
+                let end = self.chars.peek().map_or(self.s.len(), |&(offset, _)| offset);
+                match &self.s[start..end] {
+                    "old" => Ok(Expr::Old),
+                    word => word.parse().map(Expr::Const).map_err(|_| ParseExprError {
+                        column: self.s[..start].chars().count() + 1,
+                    }),
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            s,
+            chars: s.char_indices().peekable(),
+        };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.error()),
+        }
+    }
+}
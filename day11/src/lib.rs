@@ -1,123 +1,476 @@
-use std::{cmp, fmt::Display, str::Lines};
+use std::{
+    error::Error,
+    fmt::Display,
+    str::{FromStr, Lines},
+};
 
-use itertools::Itertools;
+mod cycles;
+mod expr;
+mod sim;
+pub use cycles::inspections;
+pub use expr::{BinOp, Expr, ParseExprError};
+pub use sim::{Simulation, Snapshot, Snapshots, Worry};
 
-#[derive(Debug, Clone)]
-enum Operation {
-    Increment(u64),
-    Scale(u64),
-    Square,
+/// How a monkey decides which monkey to throw an item to, like `divisible by 23` or `greater than 100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Predicate {
+    DivisibleBy(u64),
+    GreaterThan(u64),
+    LessThan(u64),
+    EqualTo(u64),
 }
 
-impl Operation {
-    fn parse(s: &str) -> Self {
-        if s == "new = old * old" {
-            return Self::Square;
+impl Predicate {
+    pub fn holds(self, worry: u64) -> bool {
+        match self {
+            Predicate::DivisibleBy(n) => worry.is_multiple_of(n),
+            Predicate::GreaterThan(n) => worry > n,
+            Predicate::LessThan(n) => worry < n,
+            Predicate::EqualTo(n) => worry == n,
         }
+    }
+}
 
-        let mut parts = s.split(' ').skip(3);
-        let op = parts.next().unwrap();
-        let rhs = parts.next().unwrap().parse().unwrap();
-        match op {
-            "+" => Self::Increment(rhs),
-            "*" => Self::Scale(rhs),
-            _ => unreachable!(),
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsePredicateError;
+
+impl Display for ParsePredicateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "expected `divisible by`, `greater than`, `less than` or `equal to` and a number, \
+             which can't be zero for `divisible by`",
+        )
     }
+}
 
-    fn apply(&self, old: u64) -> u64 {
-        match self {
-            Operation::Increment(m) => old + m,
-            Operation::Scale(m) => old * m,
-            Operation::Square => old * old,
+impl Error for ParsePredicateError {}
+
+impl FromStr for Predicate {
+    type Err = ParsePredicateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, n) = s.rsplit_once(' ').ok_or(ParsePredicateError)?;
+        let n = n.parse().map_err(|_| ParsePredicateError)?;
+        match kind {
+            // Nothing is divisible by zero, and it'd leave nothing to keep worry levels modulo.
+            "divisible by" if n == 0 => Err(ParsePredicateError),
+            "divisible by" => Ok(Predicate::DivisibleBy(n)),
+            "greater than" => Ok(Predicate::GreaterThan(n)),
+            "less than" => Ok(Predicate::LessThan(n)),
+            "equal to" => Ok(Predicate::EqualTo(n)),
+            _ => Err(ParsePredicateError),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct Monkey {
-    items: Vec<u64>,
-    operation: Operation,
-    test: u64,
-    targets: (usize, usize),
-    inspected: usize,
+/// Why a simulation couldn't be carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationError {
+    /// Without relief, worry levels are kept small by working modulo the product of the divisibility tests. That
+    /// only gives the right answers if every operation sticks to `+` and `*` and every test is divisibility, and
+    /// this monkey's doesn't.
+    NotModular { monkey: usize },
+    /// This monkey's operation overflowed, went below zero or divided by zero.
+    Arithmetic { monkey: usize },
+    /// The item this monkey starts out with at this worry level didn't go round a loop soon enough for
+    /// [`inspections`] to skip ahead.
+    NoLoop { monkey: usize, worry: u64 },
 }
 
-impl Monkey {
-    fn parse(lines: &mut Lines) -> Self {
-        lines.next().unwrap();
-        let items = lines
-            .next()
-            .unwrap()
-            .split_once(": ")
-            .unwrap()
-            .1
-            .split(", ")
-            .map(|n| n.parse().unwrap())
-            .collect();
-        let operation = Operation::parse(lines.next().unwrap().split_once(": ").unwrap().1);
-        let test = lines.next().unwrap().rsplit_once(' ').unwrap().1.parse().unwrap();
-        let targets = (
-            lines.next().unwrap().rsplit_once(' ').unwrap().1.parse().unwrap(),
-            lines.next().unwrap().rsplit_once(' ').unwrap().1.parse().unwrap(),
-        );
-        Self {
-            items,
-            operation,
-            test,
-            targets,
-            inspected: 0,
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::NotModular { monkey } => write!(
+                f,
+                "monkey {monkey} subtracts, divides or doesn't test for divisibility, \
+                 so worry levels can't be kept small by working modulo its test"
+            ),
+            SimulationError::Arithmetic { monkey } => write!(
+                f,
+                "monkey {monkey}'s operation overflowed, went below zero or divided by zero"
+            ),
+            SimulationError::NoLoop { monkey, worry } => write!(
+                f,
+                "monkey {monkey}'s item with worry level {worry} was inspected {} times without going round a loop",
+                cycles::MAX_PATH
+            ),
         }
     }
+}
+
+impl Error for SimulationError {}
 
-    fn turn(&mut self, divide_by_three: bool, lcm: u64, buf: &mut [Vec<u64>]) {
-        self.inspected += self.items.len();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMonkeyError {
+    /// A line that's missing or isn't laid out like the puzzle's.
+    Syntax {
+        monkey: usize,
+    },
+    Operation {
+        monkey: usize,
+        source: ParseExprError,
+    },
+    Test {
+        monkey: usize,
+        source: ParsePredicateError,
+    },
+    /// Throwing to a monkey that isn't there.
+    BadTarget {
+        monkey: usize,
+        target: usize,
+    },
+}
 
-        for mut item in self.items.drain(..) {
-            item = self.operation.apply(item) % lcm;
-            if divide_by_three {
-                item /= 3;
+impl Display for ParseMonkeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseMonkeyError::Syntax { monkey } => write!(f, "monkey {monkey}: expected a line like the puzzle's"),
+            ParseMonkeyError::Operation { monkey, source } => write!(f, "monkey {monkey}: invalid operation: {source}"),
+            ParseMonkeyError::Test { monkey, source } => write!(f, "monkey {monkey}: invalid test: {source}"),
+            ParseMonkeyError::BadTarget { monkey, target } => {
+                write!(f, "monkey {monkey}: there's no monkey {target} to throw to")
             }
-            let target = if item % self.test == 0 {
-                self.targets.0
-            } else {
-                self.targets.1
-            };
-            buf[target].push(item);
         }
     }
 }
 
-fn simulate(mut monkeys: Vec<Monkey>, rounds: usize, divide_by_three: bool) -> usize {
-    let lcm = monkeys.iter().fold(1, |acc, monkey| num_integer::lcm(acc, monkey.test));
+impl Error for ParseMonkeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseMonkeyError::Operation { source, .. } => Some(source),
+            ParseMonkeyError::Test { source, .. } => Some(source),
+            ParseMonkeyError::Syntax { .. } | ParseMonkeyError::BadTarget { .. } => None,
+        }
+    }
+}
 
-    let mut buf = vec![Vec::with_capacity(32); monkeys.len()];
+/// One monkey's starting items and the rules it follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monkey {
+    pub items: Vec<u64>,
+    pub operation: Expr,
+    pub test: Predicate,
+    /// Who to throw to when the test passes, and who to throw to when it doesn't.
+    pub targets: (usize, usize),
+}
 
-    for _ in 0..rounds {
-        for (idx, monkey) in monkeys.iter_mut().enumerate() {
-            monkey.items.append(&mut buf[idx]);
-            monkey.turn(divide_by_three, lcm, &mut buf);
-        }
+impl Monkey {
+    /// Parse monkey number `monkey`, leaving its targets to be checked once every monkey is known.
+    fn parse(lines: &mut Lines, monkey: usize) -> Result<Self, ParseMonkeyError> {
+        let syntax = ParseMonkeyError::Syntax { monkey };
+        let mut field = |sep| lines.next().and_then(|line| Some(line.split_once(sep)?.1.trim()));
+
+        field(':').ok_or(syntax)?;
+        let items = field(':')
+            .ok_or(syntax)?
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse().map_err(|_| syntax))
+            .collect::<Result<_, _>>()?;
+        let operation = field('=')
+            .ok_or(syntax)?
+            .parse()
+            .map_err(|source| ParseMonkeyError::Operation { monkey, source })?;
+        let test = field(':')
+            .ok_or(syntax)?
+            .parse()
+            .map_err(|source| ParseMonkeyError::Test { monkey, source })?;
+        let mut target = || {
+            let line = lines.next()?;
+            line.rsplit_once(' ')?.1.parse().ok()
+        };
+        let targets = (target().ok_or(syntax)?, target().ok_or(syntax)?);
+        Ok(Self {
+            items,
+            operation,
+            test,
+            targets,
+        })
     }
+}
 
-    monkeys
-        .iter()
-        .map(|monkey| cmp::Reverse(monkey.inspected))
-        .k_smallest(2)
-        .map(|cmp::Reverse(n)| n)
-        .product::<usize>()
+/// The product of the two largest numbers of items inspected after `rounds` rounds; see [`Simulation::new`].
+fn simulate(monkeys: &[Monkey], rounds: usize, relief: bool) -> Result<usize, SimulationError> {
+    let mut simulation = Simulation::new(monkeys, relief)?;
+    simulation.run(rounds)?;
+    Ok(simulation.monkey_business())
 }
 
-#[inline]
-pub fn solve() -> (impl Display, impl Display) {
-    let mut lines = include_str!("input.txt").trim().lines();
+/// The same as [`simulate`], but worked out with [`inspections`] instead of playing every round.
+fn extrapolate(monkeys: &[Monkey], rounds: u64, relief: bool) -> Result<u64, SimulationError> {
+    let mut inspected = inspections(monkeys, rounds, relief)?;
+    inspected.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(inspected.iter().take(2).product())
+}
+
+/// Monkeys separated by blank lines.
+pub fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseMonkeyError> {
+    let mut lines = input.trim().lines();
     let mut monkeys = Vec::with_capacity(8);
     loop {
-        monkeys.push(Monkey::parse(&mut lines));
+        monkeys.push(Monkey::parse(&mut lines, monkeys.len())?);
         if lines.next().is_none() {
             break;
         }
     }
+    for (idx, monkey) in monkeys.iter().enumerate() {
+        if let Some(target) = [monkey.targets.0, monkey.targets.1]
+            .into_iter()
+            .find(|&target| target >= monkeys.len())
+        {
+            return Err(ParseMonkeyError::BadTarget { monkey: idx, target });
+        }
+    }
+    Ok(monkeys)
+}
+
+#[inline]
+pub fn solve() -> (impl Display, impl Display) {
+    let monkeys = parse_monkeys(include_str!("input.txt")).unwrap();
+    (
+        simulate(&monkeys, 20, true).unwrap(),
+        extrapolate(&monkeys, 10_000, false).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    const SAMPLE: &str = "\
+Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+    #[test]
+    fn test_sample() {
+        let monkeys = parse_monkeys(SAMPLE).unwrap();
+        assert_eq!(simulate(&monkeys, 20, true), Ok(10605));
+        assert_eq!(simulate(&monkeys, 10_000, false), Ok(2713310158));
+        assert_eq!(extrapolate(&monkeys, 20, true), Ok(10605));
+        assert_eq!(extrapolate(&monkeys, 10_000, false), Ok(2713310158));
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let monkeys = parse_monkeys(SAMPLE).unwrap();
+        let mut simulation = Simulation::new(&monkeys, true).unwrap();
+        let first = simulation.snapshots(20).next().unwrap().unwrap();
+        assert_eq!(first.round, 1);
+        assert_eq!(
+            first.items,
+            [
+                vec![20, 23, 27, 26],
+                vec![2080, 25, 167, 207, 401, 1046],
+                vec![],
+                vec![]
+            ]
+        );
+        assert_eq!(first.inspected, [2, 4, 3, 5]);
+
+        let last = simulation.snapshots(19).last().unwrap().unwrap();
+        assert_eq!((last.round, last.inspected), (20, vec![101, 95, 7, 105]));
+    }
+
+    #[test]
+    fn test_exact() {
+        // Without relief the exact worry levels get huge, but tell the monkeys to do the same as the reduced ones.
+        let monkeys = parse_monkeys(SAMPLE).unwrap();
+        let mut reduced = Simulation::new(&monkeys, false).unwrap();
+        let mut exact = Simulation::exact(&monkeys, false);
+        for (reduced, exact) in reduced.snapshots(20).zip(exact.snapshots(20)) {
+            assert_eq!(reduced.unwrap().inspected, exact.unwrap().inspected);
+        }
+        assert_eq!(exact.inspected(), [99, 97, 8, 103]);
+
+        // With relief, both work out worry levels exactly.
+        for variant in [
+            SAMPLE.replace("old + 6", "(old + 2) / 2 - 1"),
+            SAMPLE.replace("divisible by 13", "less than 1000"),
+        ] {
+            let monkeys = parse_monkeys(&variant).unwrap();
+            let mut small = Simulation::new(&monkeys, true).unwrap();
+            let mut exact = Simulation::exact(&monkeys, true);
+            small.run(20).unwrap();
+            exact.run(20).unwrap();
+            let exact = exact.snapshot();
+            let small = small.snapshot();
+            assert_eq!(small.inspected, exact.inspected);
+            assert!(small
+                .items
+                .iter()
+                .flatten()
+                .map(|&item| BigUint::from(item))
+                .eq(exact.items.into_iter().flatten()));
+        }
+
+        let monkeys = parse_monkeys(&SAMPLE.replace("old + 3", "old - 100")).unwrap();
+        assert_eq!(
+            Simulation::exact(&monkeys, true).run(20),
+            Err(SimulationError::Arithmetic { monkey: 3 })
+        );
+    }
+
+    #[test]
+    fn test_inspections() {
+        let monkeys = parse_monkeys(SAMPLE).unwrap();
+        // With relief, items that go through monkey 2 keep getting more worrying until their worry levels overflow.
+        for (relief, rounds) in [(false, 300), (true, 20)] {
+            let mut simulation = Simulation::new(&monkeys, relief).unwrap();
+            assert_eq!(inspections(&monkeys, 0, relief), Ok(vec![0; 4]));
+            for snapshot in simulation.snapshots(rounds) {
+                let snapshot = snapshot.unwrap();
+                let inspected = snapshot.inspected.iter().map(|&n| n as u64).collect::<Vec<_>>();
+                assert_eq!(inspections(&monkeys, snapshot.round as u64, relief), Ok(inspected));
+            }
+        }
+
+        assert_eq!(
+            inspections(&monkeys, 10_000, false),
+            Ok(vec![52166, 47830, 1938, 52013])
+        );
+        assert_eq!(
+            inspections(&monkeys, 1_000_000_000_000, false),
+            Ok(vec![5217653508757, 4782346491239, 193256578955, 5202028508760])
+        );
+
+        // With relief, the item's worry level goes up by one every round, so it never comes back round.
+        let creeping = "\
+Monkey 0:
+  Starting items: 5
+  Operation: new = old * 3 + 3
+  Test: divisible by 2
+    If true: throw to monkey 1
+    If false: throw to monkey 1
 
-    (simulate(monkeys.clone(), 20, true), simulate(monkeys, 10_000, false))
+Monkey 1:
+  Starting items:
+  Operation: new = old * 3
+  Test: divisible by 3
+    If true: throw to monkey 0
+    If false: throw to monkey 0
+";
+        let monkeys = parse_monkeys(creeping).unwrap();
+        assert_eq!(inspections(&monkeys, 20, true), Ok(vec![20, 20]));
+        assert_eq!(
+            inspections(&monkeys, 1_000_000_000_000, true),
+            Err(SimulationError::NoLoop { monkey: 0, worry: 5 })
+        );
+    }
+
+    #[test]
+    fn test_expr() {
+        let expr = "(old + 3) * old - 10 / 2".parse::<Expr>().unwrap();
+        assert_eq!(expr.to_string(), "((old + 3) * old) - (10 / 2)");
+        assert_eq!(expr.eval(4), Some(23));
+        assert_eq!(expr.eval(u64::MAX), None);
+        assert!(!expr.is_modular());
+        assert_eq!("old*old".parse::<Expr>().unwrap().to_string(), "old * old");
+
+        let expr = "2 - old * 3".parse::<Expr>().unwrap();
+        assert_eq!(expr.eval(1), None);
+        assert!(!expr.is_modular());
+        let expr = "2 + old * 3".parse::<Expr>().unwrap();
+        assert!(expr.is_modular());
+        assert_eq!(expr.eval_mod(4, 7), 0);
+
+        assert_eq!("old + ".parse::<Expr>(), Err(ParseExprError { column: 7 }));
+        assert_eq!("(old + 1".parse::<Expr>(), Err(ParseExprError { column: 9 }));
+        assert_eq!("old + new".parse::<Expr>(), Err(ParseExprError { column: 7 }));
+        assert_eq!("old 3".parse::<Expr>(), Err(ParseExprError { column: 5 }));
+    }
+
+    #[test]
+    fn test_predicates() {
+        assert_eq!("greater than 100".parse(), Ok(Predicate::GreaterThan(100)));
+        assert_eq!("divisible by".parse::<Predicate>(), Err(ParsePredicateError));
+        assert_eq!("divisible by 0".parse::<Predicate>(), Err(ParsePredicateError));
+
+        let halving = SAMPLE.replace("old + 6", "old / 2");
+        assert_eq!(simulate(&parse_monkeys(&halving).unwrap(), 20, true), Ok(17365));
+        assert_eq!(
+            simulate(&parse_monkeys(&halving).unwrap(), 20, false),
+            Err(SimulationError::NotModular { monkey: 1 })
+        );
+
+        let comparing = SAMPLE.replace("divisible by 13", "less than 1000");
+        assert_eq!(simulate(&parse_monkeys(&comparing).unwrap(), 20, true), Ok(10500));
+        assert_eq!(
+            simulate(&parse_monkeys(&comparing).unwrap(), 20, false),
+            Err(SimulationError::NotModular { monkey: 2 })
+        );
+
+        let subtracting = SAMPLE.replace("old + 3", "old - 100");
+        assert_eq!(
+            simulate(&parse_monkeys(&subtracting).unwrap(), 20, false),
+            Err(SimulationError::NotModular { monkey: 3 })
+        );
+
+        let squaring = SAMPLE.replace("old + 3", "old * old * old");
+        assert_eq!(
+            simulate(&parse_monkeys(&squaring).unwrap(), 20, true),
+            Err(SimulationError::Arithmetic { monkey: 3 })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_monkeys(&SAMPLE.replace("old * old", "old ** old")),
+            Err(ParseMonkeyError::Operation {
+                monkey: 2,
+                source: ParseExprError { column: 6 }
+            })
+        );
+        assert_eq!(
+            parse_monkeys(&SAMPLE.replace("divisible by 19", "divisible by 0")),
+            Err(ParseMonkeyError::Test {
+                monkey: 1,
+                source: ParsePredicateError
+            })
+        );
+        assert_eq!(
+            parse_monkeys(&SAMPLE.replace("79, 60", "79, sixty")),
+            Err(ParseMonkeyError::Syntax { monkey: 2 })
+        );
+        assert_eq!(
+            parse_monkeys(&SAMPLE.replace("monkey 3\n\nMonkey 3", "monkey 4\n\nMonkey 3")),
+            Err(ParseMonkeyError::BadTarget { monkey: 2, target: 4 })
+        );
+        assert_eq!(
+            parse_monkeys("Monkey 0:\n  Starting items: 1"),
+            Err(ParseMonkeyError::Syntax { monkey: 0 })
+        );
+
+        let empty = parse_monkeys(&SAMPLE.replace("Starting items: 74", "Starting items:")).unwrap();
+        assert_eq!(empty[3].items, []);
+    }
 }
@@ -0,0 +1,210 @@
+use std::{cmp, fmt::Debug, mem};
+
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+use crate::{Expr, Monkey, Predicate, SimulationError};
+
+/// A worry level.
+pub trait Worry: Clone + Debug + From<u64> {
+    /// The worry level once the monkey's operation is done with it, worked out modulo `modulus` if there is one,
+    /// or `None` if it can't be worked out.
+    fn inspect(&self, operation: &Expr, modulus: Option<u64>) -> Option<Self>;
+
+    /// Divide by three, rounding down.
+    fn relieve(&mut self);
+
+    fn satisfies(&self, predicate: Predicate) -> bool;
+}
+
+impl Worry for u64 {
+    fn inspect(&self, operation: &Expr, modulus: Option<u64>) -> Option<Self> {
+        match modulus {
+            Some(modulus) => Some(operation.eval_mod(*self, modulus)),
+            None => operation.eval(*self),
+        }
+    }
+
+    fn relieve(&mut self) {
+        *self /= 3;
+    }
+
+    fn satisfies(&self, predicate: Predicate) -> bool {
+        predicate.holds(*self)
+    }
+}
+
+/// Never kept modulo anything, so it only fails to go below zero or divide by zero.
+impl Worry for BigUint {
+    fn inspect(&self, operation: &Expr, _modulus: Option<u64>) -> Option<Self> {
+        operation.eval_big(self)
+    }
+
+    fn relieve(&mut self) {
+        *self /= 3u32;
+    }
+
+    fn satisfies(&self, predicate: Predicate) -> bool {
+        match predicate {
+            Predicate::DivisibleBy(n) => self % n == BigUint::ZERO,
+            Predicate::GreaterThan(n) => *self > BigUint::from(n),
+            Predicate::LessThan(n) => *self < BigUint::from(n),
+            Predicate::EqualTo(n) => *self == BigUint::from(n),
+        }
+    }
+}
+
+impl Monkey {
+    /// Inspect an item, giving its new worry level and who it gets thrown to, or `None` if the operation can't be
+    /// worked out.
+    pub(crate) fn throw<W: Worry>(&self, item: &W, relief: bool, modulus: Option<u64>) -> Option<(W, usize)> {
+        let mut item = item.inspect(&self.operation, modulus)?;
+        if relief {
+            item.relieve();
+        }
+        let target = if item.satisfies(self.test) {
+            self.targets.0
+        } else {
+            self.targets.1
+        };
+        Some((item, target))
+    }
+}
+
+/// What to keep worry levels modulo for [`Simulation::new`]: nothing with relief, or else the product of the tests.
+pub(crate) fn modulus(monkeys: &[Monkey], relief: bool) -> Result<Option<u64>, SimulationError> {
+    if relief {
+        return Ok(None);
+    }
+    let mut lcm = 1;
+    for (idx, monkey) in monkeys.iter().enumerate() {
+        match monkey.test {
+            Predicate::DivisibleBy(n) if monkey.operation.is_modular() => lcm = num_integer::lcm(lcm, n),
+            _ => return Err(SimulationError::NotModular { monkey: idx }),
+        }
+    }
+    Ok(Some(lcm))
+}
+
+/// Where everything is after some number of rounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<W> {
+    pub round: usize,
+    /// The worry level of every item each monkey is holding, in the order it'll inspect them.
+    pub items: Vec<Vec<W>>,
+    /// How many items each monkey has inspected so far.
+    pub inspected: Vec<usize>,
+}
+
+/// Monkeys throwing items around, round by round.
+#[derive(Debug, Clone)]
+pub struct Simulation<'m, W> {
+    monkeys: &'m [Monkey],
+    relief: bool,
+    modulus: Option<u64>,
+    round: usize,
+    items: Vec<Vec<W>>,
+    inspected: Vec<usize>,
+}
+
+impl<'m> Simulation<'m, u64> {
+    /// With relief, worry levels are divided by three after each inspection and are worked out exactly. Without
+    /// it they're kept modulo the product of the tests, which needs every operation to stick to `+` and `*` and
+    /// every test to be divisibility.
+    pub fn new(monkeys: &'m [Monkey], relief: bool) -> Result<Self, SimulationError> {
+        Ok(Self::with_modulus(monkeys, relief, modulus(monkeys, relief)?))
+    }
+}
+
+impl<'m> Simulation<'m, BigUint> {
+    /// Work out every worry level exactly, however big it gets. That's slow enough to only be any use for a few
+    /// rounds without relief, but works whatever the operations and tests are.
+    pub fn exact(monkeys: &'m [Monkey], relief: bool) -> Self {
+        Self::with_modulus(monkeys, relief, None)
+    }
+}
+
+impl<'m, W: Worry> Simulation<'m, W> {
+    fn with_modulus(monkeys: &'m [Monkey], relief: bool, modulus: Option<u64>) -> Self {
+        Self {
+            monkeys,
+            relief,
+            modulus,
+            round: 0,
+            items: monkeys
+                .iter()
+                .map(|monkey| monkey.items.iter().map(|&item| W::from(item)).collect())
+                .collect(),
+            inspected: vec![0; monkeys.len()],
+        }
+    }
+
+    /// Let every monkey take a turn.
+    pub fn round(&mut self) -> Result<(), SimulationError> {
+        for (idx, monkey) in self.monkeys.iter().enumerate() {
+            let mut items = mem::take(&mut self.items[idx]);
+            self.inspected[idx] += items.len();
+            for item in items.drain(..) {
+                let (item, target) = monkey
+                    .throw(&item, self.relief, self.modulus)
+                    .ok_or(SimulationError::Arithmetic { monkey: idx })?;
+                self.items[target].push(item);
+            }
+            // Hang on to the allocation, along with anything the monkey threw to itself.
+            items.append(&mut self.items[idx]);
+            self.items[idx] = items;
+        }
+        self.round += 1;
+        Ok(())
+    }
+
+    pub fn run(&mut self, rounds: usize) -> Result<(), SimulationError> {
+        (0..rounds).try_for_each(|_| self.round())
+    }
+
+    /// Play up to `rounds` more rounds, taking a snapshot after each one.
+    pub fn snapshots(&mut self, rounds: usize) -> Snapshots<'_, 'm, W> {
+        Snapshots {
+            simulation: self,
+            rounds,
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            round: self.round,
+            items: self.items.clone(),
+            inspected: self.inspected.clone(),
+        }
+    }
+
+    /// How many items each monkey has inspected so far.
+    pub fn inspected(&self) -> &[usize] {
+        &self.inspected
+    }
+
+    /// The product of the two largest numbers of items inspected.
+    pub fn monkey_business(&self) -> usize {
+        self.inspected
+            .iter()
+            .map(|&n| cmp::Reverse(n))
+            .k_smallest(2)
+            .map(|cmp::Reverse(n)| n)
+            .product()
+    }
+}
+
+/// An iterator playing rounds and yielding a snapshot after each one.
+pub struct Snapshots<'s, 'm, W> {
+    simulation: &'s mut Simulation<'m, W>,
+    rounds: usize,
+}
+
+impl<W: Worry> Iterator for Snapshots<'_, '_, W> {
+    type Item = Result<Snapshot<W>, SimulationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rounds = self.rounds.checked_sub(1)?;
+        Some(self.simulation.round().map(|()| self.simulation.snapshot()))
+    }
+}
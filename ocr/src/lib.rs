@@ -0,0 +1,146 @@
+//! Reading the capital letters that some puzzles draw, in the 4x6 font they're always drawn in.
+//!
+//! Letters sit in cells five pixels wide: four for the glyph, then a blank column, though the last cell may
+//! leave its blank column out.
+use std::{error::Error, fmt::Display};
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+/// A glyph packed into 24 bits, top row first, leftmost pixel highest.
+const fn glyph(rows: [&str; GLYPH_HEIGHT]) -> u32 {
+    let mut bits = 0;
+    let mut y = 0;
+    while y < GLYPH_HEIGHT {
+        let row = rows[y].as_bytes();
+        let mut x = 0;
+        while x < GLYPH_WIDTH {
+            bits = bits << 1 | (row[x] == b'#') as u32;
+            x += 1;
+        }
+        y += 1;
+    }
+    bits
+}
+
+const FONT: [(char, u32); 18] = [
+    ('A', glyph([".##.", "#..#", "#..#", "####", "#..#", "#..#"])),
+    ('B', glyph(["###.", "#..#", "###.", "#..#", "#..#", "###."])),
+    ('C', glyph([".##.", "#..#", "#...", "#...", "#..#", ".##."])),
+    ('E', glyph(["####", "#...", "###.", "#...", "#...", "####"])),
+    ('F', glyph(["####", "#...", "###.", "#...", "#...", "#..."])),
+    ('G', glyph([".##.", "#..#", "#...", "#.##", "#..#", ".###"])),
+    ('H', glyph(["#..#", "#..#", "####", "#..#", "#..#", "#..#"])),
+    ('I', glyph([".###", "..#.", "..#.", "..#.", "..#.", ".###"])),
+    ('J', glyph(["..##", "...#", "...#", "...#", "#..#", ".##."])),
+    ('K', glyph(["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"])),
+    ('L', glyph(["#...", "#...", "#...", "#...", "#...", "####"])),
+    ('O', glyph([".##.", "#..#", "#..#", "#..#", "#..#", ".##."])),
+    ('P', glyph(["###.", "#..#", "#..#", "###.", "#...", "#..."])),
+    ('R', glyph(["###.", "#..#", "#..#", "###.", "#.#.", "#..#"])),
+    ('S', glyph([".###", "#...", "#...", ".##.", "...#", "###."])),
+    ('U', glyph(["#..#", "#..#", "#..#", "#..#", "#..#", ".##."])),
+    ('Y', glyph(["#...", "#...", ".#.#", "..#.", "..#.", "..#."])),
+    ('Z', glyph(["####", "...#", "..#.", ".#..", "#...", "####"])),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// The drawing isn't six pixels tall, or is empty.
+    BadSize,
+    /// The glyph in cell `index`, counting from zero, isn't a letter we know. It's drawn with `#` and `.`.
+    UnknownGlyph { index: usize, glyph: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::BadSize => write!(f, "letters have to be drawn {GLYPH_HEIGHT} pixels tall"),
+            OcrError::UnknownGlyph { index, glyph } => write!(f, "glyph {index} isn't a letter:\n{glyph}"),
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+/// Read the letters in a drawing given as a row-by-row list of pixels, `width` pixels to a row.
+pub fn decode(pixels: &[bool], width: usize) -> Result<String, OcrError> {
+    if width == 0 || pixels.len() != width * GLYPH_HEIGHT {
+        return Err(OcrError::BadSize);
+    }
+
+    (0..width.div_ceil(CELL_WIDTH))
+        .map(|index| {
+            let left = index * CELL_WIDTH;
+            let lit = |x: usize, y: usize| x < width && pixels[y * width + x];
+            let bits = (0..GLYPH_HEIGHT)
+                .flat_map(|y| (left..left + GLYPH_WIDTH).map(move |x| (x, y)))
+                .fold(0, |bits, (x, y)| bits << 1 | lit(x, y) as u32);
+            FONT.iter()
+                .find(|&&(_, glyph)| glyph == bits)
+                .map(|&(letter, _)| letter)
+                .ok_or_else(|| OcrError::UnknownGlyph {
+                    index,
+                    glyph: (0..GLYPH_HEIGHT)
+                        .map(|y| {
+                            (left..left + GLYPH_WIDTH)
+                                .map(|x| if lit(x, y) { '#' } else { '.' })
+                                .collect::<String>()
+                                + "\n"
+                        })
+                        .collect(),
+                })
+        })
+        .collect()
+}
+
+/// Read the letters in a drawing made of text, where `#` and `█` are lit pixels and anything else is dark.
+/// Short lines are padded out with dark pixels.
+pub fn decode_text(drawing: &str) -> Result<String, OcrError> {
+    let rows = drawing
+        .lines()
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if rows.len() != GLYPH_HEIGHT {
+        return Err(OcrError::BadSize);
+    }
+    let pixels = rows
+        .iter()
+        .flat_map(|row| (0..width).map(|x| matches!(row.get(x), Some('#' | '█'))))
+        .collect::<Vec<_>>();
+    decode(&pixels, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let drawing = "\
+#..#.####.####.####.####.#..#..##..####
+#..#....#.#.......#.#....#..#.#..#....#
+####...#..###....#..###..####.#......#.
+#..#..#...#.....#...#....#..#.#.....#..
+#..#.#....#....#....#....#..#.#..#.#...
+#..#.####.#....####.#....#..#..##..####
+";
+        assert_eq!(decode_text(drawing).unwrap(), "HZFZFHCZ");
+        assert_eq!(
+            decode_text(&drawing.replace('#', "█").replace('.', " ")).unwrap(),
+            "HZFZFHCZ"
+        );
+
+        let smudged = drawing.replacen("#..#.", "#.##.", 1);
+        assert_eq!(
+            decode_text(&smudged),
+            Err(OcrError::UnknownGlyph {
+                index: 0,
+                glyph: "#.##\n#..#\n####\n#..#\n#..#\n#..#\n".to_owned()
+            })
+        );
+        assert_eq!(decode_text("#..#\n#..#"), Err(OcrError::BadSize));
+    }
+}
@@ -0,0 +1,31 @@
+//! Writing greyscale pictures of puzzles as binary PGM images, which just about anything can open.
+
+/// A `width` by `height` image as a binary PGM, from one grey level per pixel, row by row from the top left. 0 is
+/// black and 255 is white.
+pub fn encode(width: usize, height: usize, levels: impl IntoIterator<Item = u8>) -> Vec<u8> {
+    let mut out = format!("P5\n{width} {height}\n255\n").into_bytes();
+    let header = out.len();
+    out.extend(levels);
+    assert_eq!(out.len() - header, width * height, "expected {width}x{height} pixels");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode(3, 2, [0, 1, 2, 253, 254, 255]),
+            b"P5\n3 2\n255\n\x00\x01\x02\xfd\xfe\xff"
+        );
+        assert_eq!(encode(0, 0, []), b"P5\n0 0\n255\n");
+    }
+
+    #[test]
+    #[should_panic = "expected 2x2 pixels"]
+    fn test_wrong_size() {
+        encode(2, 2, [0; 3]);
+    }
+}